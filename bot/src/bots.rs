@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::{
+    client::{
+        certificate::CertificateVerificationMode,
+        connection::{
+            ClientEndpointConfiguration, ConnectionEvent, ConnectionFailedEvent, ConnectionLocalId,
+            ConnectionLostEvent,
+        },
+        QuinnetClient,
+    },
+    shared::channels::ChannelsConfiguration,
+};
+use common::{
+    game::{Combination, Vote},
+    protocol::{ClientMsgComm, ClientMsgRoot, ServerMsgRoot},
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{content, Settings};
+
+/// All bots of this process, keyed by their connection.
+#[derive(Resource, Debug, Default)]
+pub struct Bots {
+    bots: HashMap<ConnectionLocalId, String>,
+    answers: Answers<ConnectionLocalId>,
+}
+
+/// Answers waiting for their bot to "think", for bots identified by `K`.
+#[derive(Debug)]
+pub struct Answers<K> {
+    pending: Vec<Pending<K>>,
+}
+
+impl<K> Default for Answers<K> {
+    fn default() -> Self {
+        Self { pending: vec![] }
+    }
+}
+
+#[derive(Debug)]
+struct Pending<K> {
+    bot: K,
+    due: Duration,
+    message: ClientMsgComm,
+}
+
+impl<K: Copy + PartialEq> Answers<K> {
+    /// Queue the answers of a bot to a message, each due within `think_time` from `now`.
+    pub fn receive(
+        &mut self,
+        bot: K,
+        message: ServerMsgRoot,
+        now: Duration,
+        think_time: Duration,
+        rng: &mut impl Rng,
+    ) {
+        // Leftover answers belong to the previous phase
+        if starts_phase(&message) {
+            self.forget(bot);
+        }
+        for message in answer(message, rng) {
+            self.pending.push(Pending {
+                bot,
+                due: now + think_time.mul_f32(rng.gen()),
+                message,
+            });
+        }
+    }
+
+    /// Drop the answers of a bot.
+    pub fn forget(&mut self, bot: K) {
+        self.pending.retain(|p| p.bot != bot);
+    }
+
+    /// Take the answers whose think time is over.
+    pub fn take_due(&mut self, now: Duration) -> Vec<(K, ClientMsgComm)> {
+        let (due, pending) = self.pending.drain(..).partition(|p| p.due <= now);
+        self.pending = pending;
+        due.into_iter()
+            .map(|p: Pending<K>| (p.bot, p.message))
+            .collect()
+    }
+}
+
+pub fn start_bots(
+    mut client: ResMut<QuinnetClient>,
+    mut bots: ResMut<Bots>,
+    settings: Res<Settings>,
+) {
+    for i in 0..settings.count {
+        let id = client
+            .open_connection(
+                ClientEndpointConfiguration::from_strings(&settings.address, "0.0.0.0:0").unwrap(),
+                CertificateVerificationMode::SkipVerification,
+                ChannelsConfiguration::default(),
+            )
+            .unwrap();
+        bots.bots.insert(id, format!("{}-{}", settings.name, i));
+    }
+    info!(
+        count = settings.count,
+        address = settings.address,
+        "Bots connecting"
+    );
+}
+
+pub fn handle_connection_events(
+    mut connection: EventReader<ConnectionEvent>,
+    mut connection_failed: EventReader<ConnectionFailedEvent>,
+    mut connection_lost: EventReader<ConnectionLostEvent>,
+    mut bots: ResMut<Bots>,
+    mut exit: EventWriter<AppExit>,
+    client: Res<QuinnetClient>,
+) {
+    for event in connection.read() {
+        let (Some(name), Some(connection)) = (
+            bots.bots.get(&event.id),
+            client.get_connection_by_id(event.id),
+        ) else {
+            continue;
+        };
+        info!(name, "Bot connected");
+        connection
+            .send_message(ClientMsgRoot::Connect { name: name.clone() })
+            .ok();
+    }
+    let failed = connection_failed.read().map(|e| e.id);
+    let lost = connection_lost.read().map(|e| e.id);
+    for id in failed.chain(lost).collect::<Vec<_>>() {
+        if let Some(name) = bots.bots.remove(&id) {
+            info!(name, "Bot disconnected");
+        }
+        bots.answers.forget(id);
+        if bots.bots.is_empty() {
+            exit.send(AppExit::Success);
+        }
+    }
+}
+
+pub fn handle_server_messages(
+    mut client: ResMut<QuinnetClient>,
+    mut bots: ResMut<Bots>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::thread_rng();
+    for (id, connection) in client.connections_mut() {
+        while let Some((_, message)) = connection.try_receive_message::<ServerMsgRoot>() {
+            let now = time.elapsed();
+            bots.answers
+                .receive(*id, message, now, settings.think_time, &mut rng);
+        }
+    }
+}
+
+/// Whether a message moves the bot to another phase.
+///
+/// Progress, tallies and timer updates arrive during a phase and leave its answers queued.
+fn starts_phase(message: &ServerMsgRoot) -> bool {
+    matches!(
+        message,
+        ServerMsgRoot::Draw { .. }
+            | ServerMsgRoot::Prompt { .. }
+            | ServerMsgRoot::Combine { .. }
+            | ServerMsgRoot::Vote { .. }
+            | ServerMsgRoot::Winner { .. }
+            | ServerMsgRoot::Wait
+    )
+}
+
+/// Generate submissions for a phase.
+fn answer(message: ServerMsgRoot, rng: &mut impl Rng) -> Vec<ClientMsgComm> {
+    match message {
+        ServerMsgRoot::Draw { drawings, .. } => (0..drawings)
+            .map(|_| ClientMsgComm::SubmitDrawing(content::drawing(rng)))
+            .collect(),
+        ServerMsgRoot::Prompt { prompts, .. } => (0..prompts)
+            .map(|_| ClientMsgComm::SubmitPrompt(content::prompt(rng)))
+            .collect(),
+        ServerMsgRoot::PromptRejected(_) => vec![ClientMsgComm::SubmitPrompt(content::prompt(rng))],
+        ServerMsgRoot::Combine {
+            drawings, prompts, ..
+        } => {
+            let (Some(drawing), Some(prompt)) = (drawings.choose(rng), prompts.choose(rng)) else {
+                return vec![];
+            };
+            vec![ClientMsgComm::SubmitCombination(Combination {
+                drawing: drawing.0,
+                prompt: prompt.0,
+            })]
+        }
+        ServerMsgRoot::Vote {
+            combination1,
+            combination2,
+            ..
+        } => {
+            let combination = if rng.gen() {
+                combination1.0
+            } else {
+                combination2.0
+            };
+            vec![ClientMsgComm::SubmitVote(Vote { combination })]
+        }
+        ServerMsgRoot::Winner { .. }
+        | ServerMsgRoot::Wait
+        | ServerMsgRoot::PhaseEnding
+        | ServerMsgRoot::Timer { .. }
+        | ServerMsgRoot::Host
+        | ServerMsgRoot::PromptReview { .. }
+        | ServerMsgRoot::Reveal(_)
+        | ServerMsgRoot::Tally(_)
+        | ServerMsgRoot::Progress(_)
+        | ServerMsgRoot::Pong { .. } => vec![],
+    }
+}
+
+pub fn send_pending(mut bots: ResMut<Bots>, client: Res<QuinnetClient>, time: Res<Time>) {
+    for (bot, message) in bots.answers.take_due(time.elapsed()) {
+        if let Some(connection) = client.get_connection_by_id(bot) {
+            connection.send_message(message.root()).ok();
+        }
+    }
+}
+
+pub fn stop_after_limit(mut exit: EventWriter<AppExit>, settings: Res<Settings>, time: Res<Time>) {
+    if settings.limit.is_some_and(|limit| limit <= time.elapsed()) {
+        info!("Time limit reached");
+        exit.send(AppExit::Success);
+    }
+}
+
+pub fn on_app_exit(app_exit_events: EventReader<AppExit>, mut client: ResMut<QuinnetClient>) {
+    if app_exit_events.is_empty() {
+        return;
+    }
+    for (_, connection) in client.connections() {
+        connection.send_message(ClientMsgRoot::Disconnect).ok();
+    }
+    std::thread::sleep(Duration::from_secs_f32(0.1));
+    client.close_all_connections().ok();
+}
@@ -0,0 +1,53 @@
+//! Generated submissions.
+
+use common::{
+    game::{CustomFont, Drawing, Picture, Prompt, FONT_COUNT, IMG_SIZE},
+    strokes::{self, VectorEdit, VectorStroke, BRUSH_COLORS, BRUSH_SIZES},
+};
+use rand::{seq::SliceRandom, Rng};
+
+const ADJECTIVES: [&str; 12] = [
+    "Angry", "Sleepy", "Tiny", "Giant", "Fancy", "Sneaky", "Soggy", "Shiny", "Grumpy", "Dancing",
+    "Haunted", "Spicy",
+];
+const NOUNS: [&str; 12] = [
+    "ape", "banana", "toaster", "wizard", "potato", "robot", "goose", "cactus", "pirate", "sock",
+    "volcano", "sandwich",
+];
+
+/// Random doodle made of round brush strokes, sent as strokes.
+pub fn drawing(rng: &mut impl Rng) -> Drawing {
+    let strokes = (0..rng.gen_range(3..8))
+        .map(|_| {
+            VectorEdit::Stroke(VectorStroke {
+                points: (0..rng.gen_range(2..7))
+                    .map(|_| random_point(rng))
+                    .collect(),
+                size: rng.gen_range(0..BRUSH_SIZES.len()) as u8,
+                color: rng.gen_range(0..BRUSH_COLORS.len()) as u8,
+                eraser: false,
+            })
+        })
+        .collect::<Vec<_>>();
+    Drawing {
+        drawing: Picture::Strokes(strokes::encode(&strokes)),
+        bg_color: *BRUSH_COLORS.choose(rng).unwrap(),
+    }
+}
+
+/// Random prompt from the word lists.
+pub fn prompt(rng: &mut impl Rng) -> Prompt {
+    Prompt {
+        text: format!(
+            "{} {}",
+            ADJECTIVES.choose(rng).unwrap(),
+            NOUNS.choose(rng).unwrap()
+        ),
+        font: CustomFont(rng.gen_range(0..FONT_COUNT)),
+    }
+}
+
+fn random_point(rng: &mut impl Rng) -> [i16; 2] {
+    let size = IMG_SIZE as i16;
+    [rng.gen_range(0..size), rng.gen_range(0..size)]
+}
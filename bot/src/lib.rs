@@ -0,0 +1,66 @@
+//! Headless players, and the answers they give to each phase.
+
+pub mod bots;
+pub mod content;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// Command line settings.
+#[derive(Resource, Debug)]
+pub struct Settings {
+    /// Server address.
+    pub address: String,
+    /// Number of bots to run.
+    pub count: usize,
+    /// Bot name prefix, followed by the bot number.
+    pub name: String,
+    /// Longest delay before answering a phase.
+    pub think_time: Duration,
+    /// Time after which all bots leave.
+    pub limit: Option<Duration>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:6000".to_owned(),
+            count: 2,
+            name: "bot".to_owned(),
+            think_time: Duration::from_secs(2),
+            limit: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut settings = Settings::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {arg}"))
+            };
+            match arg.as_str() {
+                "--address" => settings.address = value()?,
+                "--count" => settings.count = parse(&arg, value()?)?,
+                "--name" => settings.name = value()?,
+                "--think" => settings.think_time = parse_secs(&arg, value()?)?,
+                "--limit" => settings.limit = Some(parse_secs(&arg, value()?)?),
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+fn parse_secs(arg: &str, value: String) -> Result<Duration, String> {
+    parse(arg, value).map(Duration::from_secs_f32)
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {arg}: {value}"))
+}
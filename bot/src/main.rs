@@ -0,0 +1,46 @@
+//! Headless players for load testing and automated matches.
+//!
+//! Usage: `bot [--address ADDR] [--count N] [--name PREFIX] [--think SECS] [--limit SECS]`
+
+use std::time::Duration;
+
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+use bevy_quinnet::client::QuinnetClientPlugin;
+use bot::{
+    bots::{self, Bots},
+    Settings,
+};
+
+fn main() {
+    let settings = match Settings::from_args(std::env::args().skip(1)) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(2);
+        }
+    };
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        ))),
+        LogPlugin::default(),
+        QuinnetClientPlugin::default(),
+    ));
+    app.insert_resource(settings);
+    app.init_resource::<Bots>();
+    app.add_systems(Startup, bots::start_bots);
+    app.add_systems(
+        Update,
+        (
+            bots::handle_connection_events,
+            bots::handle_server_messages,
+            bots::send_pending,
+            bots::stop_after_limit,
+        )
+            .chain(),
+    );
+    app.add_systems(PostUpdate, bots::on_app_exit);
+    app.run();
+}
@@ -0,0 +1,187 @@
+//! Estimate of the server's clock, for phase countdowns.
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+use bevy_quinnet::client::QuinnetClient;
+use common::protocol::{ClientMsgRoot, Deadline};
+
+use crate::networking;
+
+/// Time between clock sync requests.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// Number of recent samples the estimate is picked from.
+const SAMPLES: usize = 8;
+
+/// Offset between the local and server clock, estimated from ping round trips.
+///
+/// Local time is [`Time<Real>`], so the estimate isn't thrown off by frame hitches.
+#[derive(Resource, Debug, Default)]
+pub struct ServerClock {
+    /// Round trip time and offset of recent pings, the quickest one is the most accurate.
+    samples: VecDeque<(Duration, f64)>,
+    last_ping: Option<Duration>,
+}
+
+impl ServerClock {
+    /// Record the answer to a ping.
+    pub fn add_sample(&mut self, sent: Duration, server_time: Duration, now: Duration) {
+        let rtt = now.saturating_sub(sent);
+        let offset = (server_time + rtt / 2).as_secs_f64() - now.as_secs_f64();
+        if self.samples.len() >= SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((rtt, offset));
+    }
+
+    /// Forget previous samples and match the server time exactly.
+    pub fn set(&mut self, server_time: Duration, now: Duration) {
+        self.samples.clear();
+        self.add_sample(now, server_time, now);
+    }
+
+    fn offset(&self) -> f64 {
+        self.samples
+            .iter()
+            .min_by_key(|(rtt, _)| *rtt)
+            .map(|(_, offset)| *offset)
+            .unwrap_or_default()
+    }
+
+    /// Estimated server time.
+    pub fn server_now(&self, now: Duration) -> Duration {
+        Duration::from_secs_f64((now.as_secs_f64() + self.offset()).max(0.0))
+    }
+}
+
+/// Countdown of the current phase.
+#[derive(Resource, Debug, Default)]
+pub struct PhaseTimer {
+    pub deadline: Deadline,
+    /// Server time the host paused the phase at.
+    pub paused_at: Option<Duration>,
+    /// Whether the server warned the phase is ending.
+    pub ending: bool,
+    /// Whether work in progress was already submitted for this deadline.
+    timed_out: bool,
+}
+
+impl PhaseTimer {
+    pub fn new(deadline: Deadline) -> Self {
+        Self {
+            deadline,
+            ..default()
+        }
+    }
+
+    /// Apply a deadline change from the host.
+    pub fn update(&mut self, deadline: Deadline, paused_at: Option<Duration>) {
+        // Extra time is for more work, which gets submitted again once it runs out
+        if deadline.duration > self.deadline.duration {
+            self.timed_out = false;
+            self.ending = false;
+        }
+        self.deadline = deadline;
+        self.paused_at = paused_at;
+    }
+
+    /// Time left, frozen while paused.
+    pub fn remaining(&self, clock: &ServerClock, now: Duration) -> Duration {
+        let server_now = self.paused_at.unwrap_or_else(|| clock.server_now(now));
+        self.deadline.at.saturating_sub(server_now)
+    }
+
+    /// Whether work in progress should be submitted, once the countdown runs out or the server
+    /// warns the phase is ending. Only true once per deadline.
+    pub fn take_timeout(&mut self, clock: &ServerClock, now: Duration) -> bool {
+        let out_of_time = self.ending || self.remaining(clock, now).is_zero();
+        if self.timed_out || !out_of_time {
+            return false;
+        }
+        self.timed_out = true;
+        true
+    }
+}
+
+pub fn reset_clock(mut clock: ResMut<ServerClock>) {
+    *clock = ServerClock::default();
+}
+
+pub fn send_pings(
+    mut client: ResMut<QuinnetClient>,
+    mut clock: ResMut<ServerClock>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed();
+    if clock
+        .last_ping
+        .is_some_and(|last| now < last + PING_INTERVAL)
+    {
+        return;
+    }
+    clock.last_ping = Some(now);
+    networking::send(&mut client, ClientMsgRoot::Ping { sent: now });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: f64) -> Duration {
+        Duration::from_secs_f64(secs)
+    }
+
+    #[test]
+    fn offset_accounts_for_half_the_round_trip() {
+        let mut clock = ServerClock::default();
+        clock.add_sample(secs(1.0), secs(100.0), secs(1.2));
+        let server_now = clock.server_now(secs(2.0));
+        assert!((server_now.as_secs_f64() - 100.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn quickest_sample_wins() {
+        let mut clock = ServerClock::default();
+        clock.add_sample(secs(0.0), secs(50.0), secs(2.0));
+        clock.add_sample(secs(3.0), secs(52.55), secs(3.1));
+        let server_now = clock.server_now(secs(4.0));
+        assert!((server_now.as_secs_f64() - 53.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn remaining_stops_at_zero() {
+        let mut clock = ServerClock::default();
+        clock.set(secs(10.0), secs(0.0));
+        let timer = PhaseTimer::new(Deadline::new(secs(10.0), secs(5.0)));
+        assert_eq!(timer.remaining(&clock, secs(2.0)), secs(3.0));
+        assert_eq!(timer.remaining(&clock, secs(7.0)), Duration::ZERO);
+    }
+
+    #[test]
+    fn pause_freezes_the_countdown() {
+        let mut clock = ServerClock::default();
+        clock.set(secs(0.0), secs(0.0));
+        let mut timer = PhaseTimer::new(Deadline::new(secs(0.0), secs(30.0)));
+        timer.update(timer.deadline, Some(secs(10.0)));
+        assert_eq!(timer.remaining(&clock, secs(100.0)), secs(20.0));
+        assert!(!timer.take_timeout(&clock, secs(100.0)));
+    }
+
+    #[test]
+    fn times_out_once_per_deadline() {
+        let mut clock = ServerClock::default();
+        clock.set(secs(0.0), secs(0.0));
+        let mut timer = PhaseTimer::new(Deadline::new(secs(0.0), secs(30.0)));
+        assert!(!timer.take_timeout(&clock, secs(10.0)));
+        timer.ending = true;
+        assert!(timer.take_timeout(&clock, secs(10.0)));
+        assert!(!timer.take_timeout(&clock, secs(40.0)));
+
+        // Only extra time allows another submission
+        timer.update(Deadline::new(secs(0.0), secs(30.0)), None);
+        assert!(!timer.take_timeout(&clock, secs(40.0)));
+        timer.update(Deadline::new(secs(0.0), secs(60.0)), None);
+        assert!(!timer.take_timeout(&clock, secs(40.0)));
+        assert!(timer.take_timeout(&clock, secs(60.0)));
+    }
+}
@@ -1,5 +1,7 @@
 pub mod barrier;
+mod clock;
 mod networking;
+mod replay;
 mod states;
 mod ui;
 
@@ -10,7 +12,7 @@ use bevy::{dev_tools::states::log_transitions, prelude::*};
 use bevy_egui::EguiPlugin;
 use bevy_quinnet::client::{QuinnetClient, QuinnetClientPlugin};
 use common::{protocol::ClientMsgRoot, transitions::IdentityTransitionsPlugin};
-use states::{ClientState, GameState, InitialResources, MenuState};
+use states::{ClientState, GameState, InitialResources, MenuState, PhaseState};
 use ui::ClientUiPlugin;
 
 fn main() {
@@ -29,7 +31,11 @@ fn main() {
     app.init_state::<ClientState>();
     app.add_sub_state::<MenuState>();
     app.add_sub_state::<GameState>();
+    app.add_sub_state::<PhaseState>();
     app.init_resource::<ConnectionData>();
+    app.init_resource::<replay::ReplayFile>();
+    app.init_resource::<clock::ServerClock>();
+    app.init_resource::<clock::PhaseTimer>();
     app.register_barrier::<InitialResources>();
 
     app.configure_sets(
@@ -48,23 +54,42 @@ fn main() {
             log_transitions::<ClientState>,
             log_transitions::<MenuState>,
             log_transitions::<GameState>,
+            log_transitions::<PhaseState>,
         )
             .chain(),
     );
 
     // ClientState::Lobby
-    app.add_systems(OnEnter(ClientState::Game), setup_client_lobby);
+    app.add_systems(
+        OnEnter(ClientState::Game),
+        (
+            clock::reset_clock,
+            networking::reset_host,
+            setup_client_lobby,
+        ),
+    );
     app.add_systems(
         Update,
         (
             networking::handle_client_disconnected_events,
             networking::handle_server_messages,
+            clock::send_pings,
         )
             .chain()
             .in_set(GameSystemOdering::Networking)
             .run_if(in_state(ClientState::Game)),
     );
 
+    // ClientState::Replay
+    app.add_systems(OnEnter(ClientState::Replay), replay::start_playback);
+    app.add_systems(OnExit(ClientState::Replay), replay::stop_playback);
+    app.add_systems(
+        Update,
+        replay::play
+            .in_set(GameSystemOdering::Networking)
+            .run_if(resource_exists::<replay::Playback>),
+    );
+
     // MenuState::Connecting
     app.add_systems(OnEnter(MenuState::Connecting), networking::start_connection);
     app.add_systems(
@@ -78,13 +103,10 @@ fn main() {
     app.run();
 }
 
-pub fn on_app_exit(app_exit_events: EventReader<AppExit>, client: Option<Res<QuinnetClient>>) {
-    if let Some(client) = client {
-        if !app_exit_events.is_empty() {
-            client
-                .connection()
-                .send_message(ClientMsgRoot::Disconnect)
-                .unwrap();
+pub fn on_app_exit(app_exit_events: EventReader<AppExit>, client: Option<ResMut<QuinnetClient>>) {
+    if let Some(mut client) = client {
+        if !app_exit_events.is_empty() && client.get_connection().is_some() {
+            networking::send(&mut client, ClientMsgRoot::Disconnect);
             sleep(Duration::from_secs_f32(0.1));
         }
     }
@@ -10,73 +10,160 @@ use bevy_quinnet::{
     },
     shared::channels::ChannelsConfiguration,
 };
-use common::protocol::ServerMsgRoot;
+use common::protocol::{ClientMsgRoot, ServerMsgRoot};
 
 use crate::{
-    states::{ClientState, GameState, MenuState},
-    ui::modes::{combine, draw, prompt, vote, winner},
+    clock::{PhaseTimer, ServerClock},
+    states::{ClientState, GameState, MenuState, PhaseState},
+    ui::{
+        host::Reviews,
+        modes::{combine, draw, prompt, vote, winner},
+        progress::Progress,
+    },
     ConnectionData,
 };
 
+/// The client is the host of the room.
+#[derive(Resource, Debug)]
+pub struct Host;
+
+/// Send a message to the server, if connected to one.
+pub fn send(client: &mut QuinnetClient, message: ClientMsgRoot) {
+    if let Some(connection) = client.get_connection_mut() {
+        connection.send_message(message).ok();
+    }
+}
+
 pub fn handle_server_messages(
     mut commands: Commands,
     mut client: ResMut<QuinnetClient>,
     mut next: ResMut<NextState<GameState>>,
+    mut phase_next: ResMut<NextState<PhaseState>>,
+    mut clock: ResMut<ServerClock>,
+    mut timer: ResMut<PhaseTimer>,
+    time: Res<Time<Real>>,
 ) {
     let Some(connection) = client.get_connection_mut() else {
         return;
     };
     while let Some((_, message)) = connection.try_receive_message::<ServerMsgRoot>() {
-        match message {
-            ServerMsgRoot::Draw { duration } => {
-                next.set(GameState::Draw);
-                commands.insert_resource(draw::Data { duration });
-            }
-            ServerMsgRoot::Prompt { duration } => {
-                next.set(GameState::Prompt);
-                commands.insert_resource(prompt::Data { duration });
-            }
-            ServerMsgRoot::Combine {
-                duration,
-                drawings,
-                prompts,
-            } => {
-                next.set(GameState::Combine);
-                commands.insert_resource(combine::Data {
-                    duration,
-                    drawings,
-                    prompts,
-                });
+        if let ServerMsgRoot::Pong { sent, server_time } = message {
+            clock.add_sample(sent, server_time, time.elapsed());
+            continue;
+        }
+        apply_message(
+            &mut commands,
+            &mut next,
+            &mut phase_next,
+            &mut timer,
+            message,
+        );
+    }
+}
+
+/// Switch to the screen of a server message.
+pub fn apply_message(
+    commands: &mut Commands,
+    next: &mut NextState<GameState>,
+    phase_next: &mut NextState<PhaseState>,
+    timer: &mut PhaseTimer,
+    message: ServerMsgRoot,
+) {
+    match message {
+        ServerMsgRoot::Draw {
+            deadline,
+            drawings,
+            theme,
+        } => {
+            next.set(GameState::Draw);
+            phase_next.set(PhaseState::Working);
+            *timer = PhaseTimer::new(deadline);
+            commands.remove_resource::<Progress>();
+            commands.insert_resource(draw::Data { drawings, theme });
+        }
+        ServerMsgRoot::Prompt { deadline, prompts } => {
+            next.set(GameState::Prompt);
+            phase_next.set(PhaseState::Working);
+            *timer = PhaseTimer::new(deadline);
+            commands.remove_resource::<Progress>();
+            commands.insert_resource(prompt::Data { prompts });
+        }
+        ServerMsgRoot::Combine {
+            deadline,
+            drawings,
+            prompts,
+        } => {
+            next.set(GameState::Combine);
+            // Players left without content sit the phase out
+            if drawings.is_empty() || prompts.is_empty() {
+                phase_next.set(PhaseState::Submitted);
+            } else {
+                phase_next.set(PhaseState::Working);
             }
-            ServerMsgRoot::Vote {
-                duration,
+            *timer = PhaseTimer::new(deadline);
+            commands.remove_resource::<Progress>();
+            commands.insert_resource(combine::Data { drawings, prompts });
+        }
+        ServerMsgRoot::Vote {
+            deadline,
+            combination1,
+            combination2,
+        } => {
+            next.set(GameState::Vote);
+            *timer = PhaseTimer::new(deadline);
+            commands.remove_resource::<Progress>();
+            commands.remove_resource::<vote::Tally>();
+            commands.insert_resource(vote::Data {
                 combination1,
                 combination2,
-            } => {
-                next.set(GameState::Vote);
-                commands.insert_resource(vote::Data {
-                    duration,
-                    combination1,
-                    combination2,
-                });
-            }
-            ServerMsgRoot::Wait => next.set(GameState::Wait),
-            ServerMsgRoot::Winner {
-                duration,
-                drawing,
-                prompt,
-            } => {
-                next.set(GameState::Winner);
-                commands.insert_resource(winner::Data {
-                    duration,
-                    drawing,
-                    prompt,
-                });
-            }
+            });
+        }
+        ServerMsgRoot::Wait => {
+            next.set(GameState::Wait);
+            commands.remove_resource::<Progress>();
+        }
+        ServerMsgRoot::PhaseEnding => timer.ending = true,
+        ServerMsgRoot::Timer {
+            deadline,
+            paused_at,
+        } => timer.update(deadline, paused_at),
+        ServerMsgRoot::Host => commands.insert_resource(Host),
+        ServerMsgRoot::PromptReview { prompt, author } => commands.add(move |world: &mut World| {
+            let mut reviews = world.get_resource_or_insert_with(Reviews::default);
+            reviews.0.push((prompt, author));
+        }),
+        ServerMsgRoot::PromptRejected(prompt) => commands.add(move |world: &mut World| {
+            let mut rejected = world.get_resource_or_insert_with(prompt::Rejected::default);
+            rejected.0.push(prompt);
+        }),
+        // Clock sync is handled by the connection
+        ServerMsgRoot::Pong { .. } => {}
+        ServerMsgRoot::Winner {
+            deadline,
+            winner,
+            loser,
+            points,
+        } => {
+            next.set(GameState::Winner);
+            *timer = PhaseTimer::new(deadline);
+            commands.remove_resource::<Progress>();
+            commands.remove_resource::<winner::Revealed>();
+            commands.insert_resource(winner::Data {
+                winner: *winner,
+                loser: *loser,
+                points,
+            });
         }
+        ServerMsgRoot::Progress(progress) => commands.insert_resource(Progress(progress)),
+        ServerMsgRoot::Tally(tally) => commands.insert_resource(vote::Tally(tally)),
+        ServerMsgRoot::Reveal(stage) => commands.insert_resource(winner::Revealed(stage)),
     }
 }
 
+pub fn reset_host(mut commands: Commands) {
+    commands.remove_resource::<Host>();
+}
+
 pub fn start_connection(mut client: ResMut<QuinnetClient>, data: Res<ConnectionData>) {
     client
         .open_connection(
@@ -0,0 +1,115 @@
+//! Watching recorded matches.
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+use common::{protocol::ServerMsgRoot, replay::Replay};
+
+use crate::{
+    clock::{PhaseTimer, ServerClock},
+    networking,
+    states::{ClientState, GameState, PhaseState},
+    ConnectionData,
+};
+
+/// Time the last screen stays up before going back to the menu.
+const END_DELAY: Duration = Duration::from_secs(5);
+
+/// Replay picked in the menu.
+#[derive(Resource, Debug)]
+pub struct ReplayFile {
+    pub path: String,
+    /// Why the last replay couldn't be played.
+    pub error: Option<String>,
+}
+
+impl Default for ReplayFile {
+    fn default() -> Self {
+        Self {
+            path: "match.replay".to_owned(),
+            error: None,
+        }
+    }
+}
+
+/// Messages of the watched player, yet to be shown.
+#[derive(Resource, Debug)]
+pub struct Playback {
+    started: Duration,
+    messages: VecDeque<(Duration, ServerMsgRoot)>,
+    last: Duration,
+}
+
+pub fn start_playback(
+    mut commands: Commands,
+    mut file: ResMut<ReplayFile>,
+    mut next: ResMut<NextState<ClientState>>,
+    mut clock: ResMut<ServerClock>,
+    data: Res<ConnectionData>,
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+) {
+    let replay = match Replay::load(&file.path) {
+        Ok(replay) => replay,
+        Err(err) => {
+            file.error = Some(format!("Failed to load replay: {err}"));
+            next.set(ClientState::Menu);
+            return;
+        }
+    };
+    // Watch the player with our name, or the first one
+    let Some(player) = replay
+        .player(&data.name)
+        .or_else(|| replay.players.first().map(|(id, _)| *id))
+    else {
+        file.error = Some("Replay has no players".to_owned());
+        next.set(ClientState::Menu);
+        return;
+    };
+    file.error = None;
+    let messages = replay
+        .received_by(player)
+        .map(|(time, message)| (time, message.clone()))
+        .collect::<VecDeque<_>>();
+    info!(player, messages = messages.len(), "Watching replay");
+    // Countdowns follow the recorded server clock
+    clock.set(replay.started, real_time.elapsed());
+    commands.insert_resource(Playback {
+        started: time.elapsed(),
+        last: messages.back().map(|(time, _)| *time).unwrap_or_default(),
+        messages,
+    });
+}
+
+pub fn stop_playback(mut commands: Commands) {
+    commands.remove_resource::<Playback>();
+}
+
+pub fn play(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut timer: ResMut<PhaseTimer>,
+    mut game_next: ResMut<NextState<GameState>>,
+    mut phase_next: ResMut<NextState<PhaseState>>,
+    mut client_next: ResMut<NextState<ClientState>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed() - playback.started;
+    while playback
+        .messages
+        .front()
+        .is_some_and(|(time, _)| *time <= now)
+    {
+        let (_, message) = playback.messages.pop_front().unwrap();
+        networking::apply_message(
+            &mut commands,
+            &mut game_next,
+            &mut phase_next,
+            &mut timer,
+            message,
+        );
+    }
+    if playback.messages.is_empty() && playback.last + END_DELAY <= now {
+        client_next.set(ClientState::Menu);
+    }
+}
@@ -11,6 +11,8 @@ pub enum ClientState {
     Menu,
     /// In a lobby.
     Game,
+    /// Watching a recorded match.
+    Replay,
 }
 
 pub struct InitialResources;
@@ -38,7 +40,7 @@ pub enum MenuState {
 }
 
 #[derive(SubStates, Debug, Clone, PartialEq, Eq, Hash, Default)]
-#[source(ClientState = ClientState::Game)]
+#[source(ClientState = ClientState::Game | ClientState::Replay)]
 pub enum GameState {
     #[default]
     Wait,
@@ -48,3 +50,14 @@ pub enum GameState {
     Vote,
     Winner,
 }
+
+/// Progress of the player in a phase they submit work in.
+#[derive(SubStates, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[source(GameState = GameState::Draw | GameState::Prompt | GameState::Combine)]
+pub enum PhaseState {
+    /// Working on submissions.
+    #[default]
+    Working,
+    /// Everything submitted, waiting for the other players.
+    Submitted,
+}
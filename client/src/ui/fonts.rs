@@ -1,7 +1,7 @@
 use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
 use bevy::prelude::*;
 use bevy_egui::EguiContext;
-use common::game::CustomFont;
+use common::game::{CustomFont, FONT_COUNT};
 use egui::{FontData, FontFamily, FontId};
 use thiserror::Error;
 
@@ -20,7 +20,7 @@ impl Plugin for FontsPlugin {
     }
 }
 
-pub const FONTS: [&str; 6] = [
+pub const FONTS: [&str; FONT_COUNT] = [
     "fonts/BLADRMF_.TTF",
     "fonts/IHATCS__.TTF",
     "fonts/Lemon Shake Shake.ttf",
@@ -0,0 +1,96 @@
+//! Match controls shown to the host.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use bevy_quinnet::client::QuinnetClient;
+use common::{
+    game::{Index, Prompt},
+    protocol::{ClientMsgRoot, HostCommand},
+};
+
+use crate::{clock::PhaseTimer, networking::send};
+
+/// Time added to the current phase by the extend button.
+const EXTEND_BY: Duration = Duration::from_secs(30);
+
+/// Prompts held back by the word filter, with the name of their author.
+#[derive(Resource, Debug, Default)]
+pub struct Reviews(pub Vec<((Index, Prompt), String)>);
+
+pub fn show_controls(
+    mut ui_ctx: Query<&mut EguiContext>,
+    mut client: ResMut<QuinnetClient>,
+    mut reviews: Option<ResMut<Reviews>>,
+    mut theme: Local<String>,
+    timer: Res<PhaseTimer>,
+) {
+    let mut ui_ctx = ui_ctx.single_mut();
+
+    let mut command = None;
+    egui::Window::new("Host")
+        .resizable(false)
+        .show(ui_ctx.get_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if timer.paused_at.is_some() {
+                    if ui.button("Resume").clicked() {
+                        command = Some(HostCommand::Resume);
+                    }
+                } else if ui.button("Pause").clicked() {
+                    command = Some(HostCommand::Pause);
+                }
+                let extend = format!("+{}s", EXTEND_BY.as_secs());
+                if ui.button(extend).clicked() {
+                    command = Some(HostCommand::Extend(EXTEND_BY));
+                }
+                if ui.button("Skip").clicked() {
+                    command = Some(HostCommand::Skip);
+                }
+            });
+            // Applies from the next drawing round on
+            ui.horizontal(|ui| {
+                ui.label("Theme");
+                ui.text_edit_singleline(&mut *theme);
+                let text = theme.trim();
+                if ui
+                    .add_enabled(!text.is_empty(), egui::Button::new("Set"))
+                    .clicked()
+                {
+                    command = Some(HostCommand::SetTheme(Some(text.to_owned())));
+                }
+                if ui.button("Clear").clicked() {
+                    theme.clear();
+                    command = Some(HostCommand::SetTheme(None));
+                }
+            });
+            let Some(reviews) = reviews.as_deref_mut() else {
+                return;
+            };
+            let mut reviewed = None;
+            for (i, ((index, prompt), author)) in reviews.0.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{author}: {}", prompt.text));
+                    if ui.button("Approve").clicked() {
+                        command = Some(HostCommand::ApprovePrompt(*index));
+                        reviewed = Some(i);
+                    }
+                    if ui.button("Reject").clicked() {
+                        command = Some(HostCommand::RejectPrompt(*index));
+                        reviewed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = reviewed {
+                reviews.0.remove(i);
+            }
+        });
+    if let Some(command) = command {
+        send(&mut client, ClientMsgRoot::Host(command));
+    }
+}
+
+/// Forget prompts left to review once the prompt phase is over.
+pub fn clear_reviews(mut commands: Commands) {
+    commands.remove_resource::<Reviews>();
+}
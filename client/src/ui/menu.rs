@@ -3,14 +3,20 @@ use std::net::SocketAddr;
 use bevy::prelude::*;
 use bevy_egui::EguiContext;
 
-use crate::{states::MenuState, ConnectionData};
+use crate::{
+    replay::ReplayFile,
+    states::{ClientState, MenuState},
+    ConnectionData,
+};
 
 use super::widgets::{root_element, validated_singleline_textbox};
 
 pub fn show(
     mut ui_ctx: Query<&mut EguiContext>,
     mut data: ResMut<ConnectionData>,
+    mut replay: ResMut<ReplayFile>,
     mut next: ResMut<NextState<MenuState>>,
+    mut client_next: ResMut<NextState<ClientState>>,
     state: Res<State<MenuState>>,
 ) {
     let mut ctx = ui_ctx.single_mut();
@@ -18,7 +24,9 @@ pub fn show(
     let address_is_valid = data.address.parse::<SocketAddr>().is_ok();
 
     root_element(ctx.get_mut(), |ui| {
-        ui.set_enabled(*state.get() == MenuState::Configuring);
+        if *state.get() != MenuState::Configuring {
+            ui.disable();
+        }
 
         egui::Grid::new("Main Menu Grid")
             .num_columns(2)
@@ -32,11 +40,23 @@ pub fn show(
                 validated_singleline_textbox(ui, address_is_valid, &mut data.address)
                     .on_hover_text("Valid IP address and port.");
                 ui.end_row();
+
+                ui.label("Replay:");
+                ui.text_edit_singleline(&mut replay.path)
+                    .on_hover_text("Replay file, watched as the player with your username.");
+                ui.end_row();
             });
 
-        let connect = ui.button("Connect").clicked();
-        if connect {
-            next.set(MenuState::Connecting);
+        ui.horizontal(|ui| {
+            if ui.button("Connect").clicked() {
+                next.set(MenuState::Connecting);
+            }
+            if ui.button("Watch replay").clicked() {
+                client_next.set(ClientState::Replay);
+            }
+        });
+        if let Some(error) = &replay.error {
+            ui.colored_label(egui::Color32::RED, error);
         }
     });
 }
@@ -1,6 +1,8 @@
 pub mod fonts;
+pub mod host;
 pub mod menu;
 pub mod modes;
+pub mod progress;
 pub mod util;
 pub mod widgets;
 
@@ -9,6 +11,7 @@ use fonts::FontsPlugin;
 use modes::ModesPlugin;
 
 use crate::{
+    networking::Host,
     states::{ClientState, GameState},
     GameSystemOdering,
 };
@@ -28,6 +31,26 @@ impl Plugin for ClientUiPlugin {
                 .run_if(in_state(GameState::Wait)),
         );
 
+        // Progress, laid out before the screen it sits next to
+        app.add_systems(
+            Update,
+            progress::show_sidebar
+                .after(GameSystemOdering::Networking)
+                .before(GameSystemOdering::StateLogic)
+                .run_if(
+                    in_state(ClientState::Game).and_then(resource_exists::<progress::Progress>),
+                ),
+        );
+
+        // Host
+        app.add_systems(
+            Update,
+            host::show_controls
+                .in_set(GameSystemOdering::StateLogic)
+                .run_if(in_state(ClientState::Game).and_then(resource_exists::<Host>)),
+        );
+        app.add_systems(OnExit(GameState::Prompt), host::clear_reviews);
+
         app.add_plugins((FontsPlugin, ModesPlugin));
     }
 }
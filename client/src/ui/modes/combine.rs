@@ -1,23 +1,22 @@
-use std::time::Duration;
-
-use bevy::{
-    prelude::*,
-    render::render_resource::{
-        Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    },
-};
+use bevy::prelude::*;
 use bevy_egui::{EguiContext, EguiUserTextures};
 use bevy_quinnet::client::QuinnetClient;
 use common::{
     app::AppExt,
-    game::{Combination, Drawing, Index, Prompt, IMG_SIZE},
+    game::{Combination, Drawing, Index, Prompt},
     protocol::ClientMsgComm,
 };
 use egui::RichText;
 
 use crate::{
-    states::GameState,
-    ui::{fonts::IntoFontFamily, widgets::root_element},
+    clock::{PhaseTimer, ServerClock},
+    networking::send,
+    states::{GameState, PhaseState},
+    ui::{
+        fonts::IntoFontFamily,
+        modes::vote::prep_drawing,
+        widgets::{countdown, drawing, root_element},
+    },
     GameSystemOdering,
 };
 
@@ -30,8 +29,12 @@ impl Plugin for ModePlugin {
             GameState::Combine,
             setup,
             teardown,
-            (draw_ui, execute_actions)
-                .chain()
+            (
+                (submit_on_timeout, draw_ui, execute_actions)
+                    .chain()
+                    .run_if(in_state(PhaseState::Working)),
+                show_submitted.run_if(in_state(PhaseState::Submitted)),
+            )
                 .in_set(GameSystemOdering::StateLogic),
         );
     }
@@ -39,14 +42,13 @@ impl Plugin for ModePlugin {
 
 #[derive(Resource, Clone)]
 pub struct Data {
-    pub duration: Duration,
     pub drawings: Vec<(Index, Drawing)>,
     pub prompts: Vec<(Index, Prompt)>,
 }
 
 #[derive(Resource)]
 pub struct Context {
-    pub duration: Duration,
+    pub submitted: bool,
     pub drawings: Vec<(Index, (Handle<Image>, egui::Color32))>,
     pub drawing_ptr: usize,
     pub prompts: Vec<(Index, Prompt)>,
@@ -73,39 +75,14 @@ fn setup(
     let data = data.clone();
 
     let mut drawings = Vec::with_capacity(data.drawings.len());
-    for drawing in data.drawings {
-        let size = Extent3d {
-            width: IMG_SIZE as u32,
-            height: IMG_SIZE as u32,
-            ..default()
-        };
-        let image = Image {
-            texture_descriptor: TextureDescriptor {
-                label: None,
-                size,
-                dimension: TextureDimension::D2,
-                format: TextureFormat::Bgra8UnormSrgb,
-                mip_level_count: 1,
-                sample_count: 1,
-                usage: TextureUsages::TEXTURE_BINDING
-                    | TextureUsages::COPY_DST
-                    | TextureUsages::RENDER_ATTACHMENT,
-                view_formats: &[],
-            },
-            data: drawing.1.drawing,
-            ..default()
-        };
-        let image_handle = images.add(image);
-        textures.add_image(image_handle.clone_weak());
-        let bg_color = drawing.1.bg_color;
-        let bg_color = egui::Color32::from_rgb(bg_color[0], bg_color[1], bg_color[2]);
-        drawings.push((drawing.0, (image_handle, bg_color)));
+    for (index, drawing) in data.drawings {
+        drawings.push((index, prep_drawing(&mut images, &mut textures, drawing)));
     }
 
     let prompts = data.prompts;
 
     commands.insert_resource(Context {
-        duration: data.duration,
+        submitted: false,
         drawings,
         prompts,
         drawing_ptr: 0,
@@ -118,11 +95,15 @@ fn draw_ui(
     mut actions: EventWriter<UiAction>,
     images: Res<EguiUserTextures>,
     ctx: Res<Context>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
 ) {
     let mut ui_ctx = ui_ctx.single_mut();
 
     root_element(ui_ctx.get_mut(), |ui| {
         ui.label("Combine");
+        countdown(ui, &timer, &clock, time.elapsed());
         egui::Grid::new("nav-buttons")
             .num_columns(3)
             .show(ui, |ui| {
@@ -159,16 +140,63 @@ fn draw_ui(
                 }
                 ui.end_row();
             });
-        if ui.button("Submit").clicked() {
+        if ui
+            .add_enabled(!ctx.submitted, egui::Button::new("Submit"))
+            .clicked()
+        {
             actions.send(UiAction::Submit);
         }
     });
 }
 
+fn show_submitted(
+    mut ui_ctx: Query<&mut EguiContext>,
+    images: Res<EguiUserTextures>,
+    ctx: Res<Context>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
+) {
+    let mut ui_ctx = ui_ctx.single_mut();
+
+    root_element(ui_ctx.get_mut(), |ui| {
+        ui.label("Combine");
+        countdown(ui, &timer, &clock, time.elapsed());
+        ui.label("Waiting for other players");
+        let (Some(submitted), Some((_, prompt))) = (
+            ctx.drawings.get(ctx.drawing_ptr),
+            ctx.prompts.get(ctx.prompt_ptr),
+        ) else {
+            ui.label("There weren't enough drawings and prompts for you this round");
+            return;
+        };
+        drawing(ui, &images, &submitted.1, 512.0);
+        ui.label(
+            RichText::new(&prompt.text)
+                .font(prompt.font.get_font_id())
+                .color(egui::Color32::WHITE),
+        );
+    });
+}
+
+/// Submit the selected pair when the timer runs out or the server warns the phase is ending.
+fn submit_on_timeout(
+    mut timer: ResMut<PhaseTimer>,
+    mut actions: EventWriter<UiAction>,
+    ctx: Res<Context>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
+) {
+    if timer.take_timeout(&clock, time.elapsed()) && !ctx.submitted {
+        actions.send(UiAction::Submit);
+    }
+}
+
 fn execute_actions(
     mut actions: ResMut<Events<UiAction>>,
     mut ctx: ResMut<Context>,
     mut client: ResMut<QuinnetClient>,
+    mut next: ResMut<NextState<PhaseState>>,
 ) {
     for action in actions.drain() {
         let drawing_count = ctx.drawings.len();
@@ -187,16 +215,19 @@ fn execute_actions(
                 ctx.prompt_ptr = (ctx.prompt_ptr + prompt_count - 1) % prompt_count;
             }
             UiAction::Submit => {
-                client
-                    .connection_mut()
-                    .send_message(
-                        ClientMsgComm::SubmitCombination(Combination {
-                            drawing: ctx.drawings[ctx.drawing_ptr].0,
-                            prompt: ctx.prompts[ctx.prompt_ptr].0,
-                        })
-                        .root(),
-                    )
-                    .ok();
+                if ctx.submitted {
+                    continue;
+                }
+                ctx.submitted = true;
+                next.set(PhaseState::Submitted);
+                send(
+                    &mut client,
+                    ClientMsgComm::SubmitCombination(Combination {
+                        drawing: ctx.drawings[ctx.drawing_ptr].0,
+                        prompt: ctx.prompts[ctx.prompt_ptr].0,
+                    })
+                    .root(),
+                );
             }
         }
     }
@@ -1,22 +1,28 @@
-use std::time::Duration;
-
 use bevy::{
     prelude::*,
-    render::{
-        render_resource::{
-            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-        },
-        view::RenderLayers,
+    render::render_resource::{
+        Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
     },
 };
 use bevy_egui::{EguiContext, EguiUserTextures};
 use bevy_quinnet::client::QuinnetClient;
-use common::{app::AppExt, game::Drawing, protocol::ClientMsgComm};
-use egui::Stroke;
+use common::{
+    app::AppExt,
+    canvas::{Canvas, Edit, Fill, Region, Stroke},
+    game::{Drawing, Picture},
+    protocol::ClientMsgComm,
+    strokes::{BRUSH_COLORS, BRUSH_SIZES, MAX_POINTS, MAX_STROKES},
+};
 
 use crate::{
-    states::GameState,
-    ui::{util::Scaler, widgets::root_element},
+    clock::{PhaseTimer, ServerClock},
+    networking::send,
+    states::{GameState, PhaseState},
+    ui::{
+        modes::vote::prep_drawing,
+        util::Scaler,
+        widgets::{countdown, drawing, root_element},
+    },
     GameSystemOdering,
 };
 
@@ -25,39 +31,29 @@ pub struct ModePlugin;
 impl Plugin for ModePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<UiAction>();
-        app.add_plugins(save_image::SaveDrawingPlugin);
-        app.insert_gizmo_config(
-            DefaultGizmoConfigGroup,
-            GizmoConfig {
-                render_layers: RenderLayers::layer(1),
-                line_joints: GizmoLineJoint::Round(32),
-                ..default()
-            },
-        );
         app.add_reentrant_statebound(
             GameState::Draw,
             setup,
             teardown,
-            (execute_actions, show_ui, send_image)
-                .chain()
+            (
+                (
+                    submit_on_timeout,
+                    execute_actions,
+                    show_ui,
+                    update_image,
+                    send_image,
+                )
+                    .chain()
+                    .run_if(in_state(PhaseState::Working)),
+                show_submitted.run_if(in_state(PhaseState::Submitted)),
+            )
                 .in_set(GameSystemOdering::StateLogic),
         );
     }
 }
 
-const BRUSH_SIZES: [f32; 5] = [3.0, 5.0, 13.0, 21.0, 43.0];
-const BRUSH_COLORS: [egui::Color32; 10] = [
-    egui::Color32::BLACK,
-    egui::Color32::GRAY,
-    egui::Color32::WHITE,
-    egui::Color32::RED,
-    egui::Color32::DARK_RED,
-    egui::Color32::GREEN,
-    egui::Color32::DARK_GREEN,
-    egui::Color32::BLUE,
-    egui::Color32::DARK_BLUE,
-    egui::Color32::GOLD,
-];
+/// Colour difference the paint bucket spreads over, so it fills anti-aliased edges too.
+const FILL_TOLERANCE: u8 = 64;
 const IMG_HALF_SIZE: f32 = 256.0;
 const IMG_SIZE: f32 = 2.0 * IMG_HALF_SIZE;
 const IMG_PADDING_HALF_SIZE: f32 = 8.0;
@@ -65,24 +61,155 @@ const IMG_PADDING: f32 = 2.0 * IMG_PADDING_HALF_SIZE;
 
 #[derive(Resource, Clone)]
 pub struct Data {
-    pub duration: Duration,
+    pub drawings: usize,
+    pub theme: Option<String>,
+}
+
+/// Edits of the canvas in order, along with undone ones that can be redone.
+#[derive(Debug, Default)]
+pub struct History {
+    edits: Vec<Edit>,
+    undone: Vec<Edit>,
+}
+
+impl History {
+    /// Add a finished edit, a new edit can't be followed by a redo.
+    pub fn push(&mut self, edit: Edit) {
+        self.edits.push(edit);
+        self.undone.clear();
+    }
+
+    /// Take back the last edit, returns whether there was one.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.edits.pop() else {
+            return false;
+        };
+        self.undone.push(edit);
+        true
+    }
+
+    /// Put back the last undone edit, returns whether there was one.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.undone.pop() else {
+            return false;
+        };
+        self.edits.push(edit);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// Points of all strokes, which can't go over [`MAX_POINTS`].
+    pub fn points(&self) -> usize {
+        self.edits
+            .iter()
+            .map(|edit| match edit {
+                Edit::Stroke(stroke) => stroke.points.len(),
+                Edit::Fill(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Whether the drawing has as many edits or points as it can be sent with.
+    pub fn is_full(&self) -> bool {
+        self.edits.len() >= MAX_STROKES || self.points() >= MAX_POINTS
+    }
+
+    pub fn clear(&mut self) {
+        *self = default();
+    }
+}
+
+/// What pressing on the canvas does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Brush,
+    /// Fill the region under the cursor with the brush colour.
+    Bucket,
 }
 
 #[derive(Resource)]
 pub struct Context {
-    pub duration: Duration,
+    pub drawings: usize,
+    pub submitted: usize,
+    pub theme: Option<String>,
     pub image_handle: Handle<Image>,
-    pub last_pos: Option<Vec2>,
+    /// Stroke being drawn.
+    pub stroke: Option<Stroke>,
+    pub history: History,
+    /// Edits of the history, painted in order.
+    pub canvas: Canvas,
+    /// Canvas with the stroke being drawn on top, painted a segment at a time.
+    pub preview: Canvas,
+    /// Part of the image behind the preview.
+    pub dirty: Option<Region>,
+    pub tool: Tool,
     pub brush_size: f32,
     pub brush_color: egui::Color32,
     pub bg_color: egui::Color32,
+    /// Drawings submitted so far, to show while waiting for other players.
+    pub submissions: Vec<(Handle<Image>, egui::Color32)>,
+}
+
+impl Context {
+    /// Paint the history again on a blank canvas.
+    fn rebuild_canvas(&mut self) {
+        self.canvas = Canvas::default();
+        for edit in self.history.edits() {
+            self.canvas.apply(edit);
+        }
+        self.reset_preview();
+    }
+
+    /// Paint the whole preview again from the canvas.
+    fn reset_preview(&mut self) {
+        self.preview = self.canvas.clone();
+        if let Some(stroke) = &self.stroke {
+            self.preview.draw(stroke);
+        }
+        self.dirty = Some(Region::FULL);
+    }
+
+    /// Paint the end of the stroke being drawn on the preview.
+    ///
+    /// Joins between segments blend twice at their anti-aliased edges, the stroke is painted
+    /// properly on the canvas once it's finished.
+    fn preview_stroke_end(&mut self) {
+        let Some(stroke) = &self.stroke else {
+            return;
+        };
+        let start = stroke.points.len().saturating_sub(2);
+        let segment = Stroke {
+            points: stroke.points[start..].to_vec(),
+            ..stroke.clone()
+        };
+        if let Some(region) = self.preview.draw(&segment) {
+            self.dirty = Some(match self.dirty {
+                Some(dirty) => dirty.union(region),
+                None => region,
+            });
+        }
+    }
 }
 
 #[derive(Event)]
 pub enum UiAction {
+    Tool(Tool),
     BrushSize(f32),
     BrushColor(egui::Color32),
     ShirtColor(egui::Color32),
+    Undo,
+    Redo,
     Submit,
 }
 
@@ -109,10 +236,7 @@ fn setup(
             format: TextureFormat::Bgra8UnormSrgb,
             mip_level_count: 1,
             sample_count: 1,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_DST
-                | TextureUsages::COPY_SRC
-                | TextureUsages::RENDER_ATTACHMENT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
             view_formats: &[],
         },
         ..default()
@@ -120,27 +244,22 @@ fn setup(
     image.resize(size);
     let image_handle = images.add(image);
     textures.add_image(image_handle.clone_weak());
-    commands.spawn((
-        Camera2dBundle {
-            camera: Camera {
-                target: image_handle.clone().into(),
-                clear_color: ClearColorConfig::None,
-                ..default()
-            },
-            ..default()
-        },
-        RenderLayers::layer(1),
-        StateScoped(GameState::Draw),
-    ));
     commands.insert_resource(Context {
-        duration: data.duration,
+        drawings: data.drawings,
+        submitted: 0,
+        theme: data.theme,
         image_handle,
-        last_pos: None,
+        stroke: None,
+        history: History::default(),
+        canvas: Canvas::default(),
+        preview: Canvas::default(),
+        dirty: None,
+        tool: Tool::Brush,
         brush_size: BRUSH_SIZES[2],
-        brush_color: BRUSH_COLORS[0],
-        bg_color: BRUSH_COLORS[2],
+        brush_color: color32(BRUSH_COLORS[0]),
+        bg_color: color32(BRUSH_COLORS[2]),
+        submissions: vec![],
     });
-    actions.send(UiAction::BrushSize(BRUSH_SIZES[2]));
 }
 
 fn teardown(mut commands: Commands, mut actions: ResMut<Events<UiAction>>) {
@@ -153,32 +272,88 @@ fn show_ui(
     mut ui_ctx: Query<&mut EguiContext>,
     mut ctx: ResMut<Context>,
     mut actions: EventWriter<UiAction>,
-    mut gizmos: Gizmos,
     window: Query<&Window>,
     images: Res<EguiUserTextures>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
 ) {
     let mut ui_ctx = ui_ctx.single_mut();
     let window = window.single();
 
     root_element(ui_ctx.get_mut(), |ui| {
         ui.label("Draw");
+        countdown(ui, &timer, &clock, time.elapsed());
+        if let Some(theme) = &ctx.theme {
+            ui.label(egui::RichText::new(format!("Theme: {theme}")).heading());
+        }
 
         ui.horizontal(|ui| {
-            show_brush_colors(ui, &mut actions);
-            show_canvas(ui, &mut ctx, &mut gizmos, &images, window);
+            show_brush_colors(ui, &ctx, &mut actions);
+            show_canvas(ui, &mut ctx, &images, window);
             show_shirt_colors(ui, &mut actions);
         });
 
         show_brushes(ui, &mut ctx, &mut actions);
-
-        if ui.button("Submit").clicked() {
+        show_history(ui, &ctx, &mut actions);
+
+        ui.label(format!("{} of {} submitted", ctx.submitted, ctx.drawings));
+        let can_submit = ctx.submitted < ctx.drawings;
+        if ui
+            .add_enabled(can_submit, egui::Button::new("Submit"))
+            .clicked()
+        {
             actions.send(UiAction::Submit);
         }
     });
 }
 
-fn show_brush_colors(ui: &mut egui::Ui, actions: &mut EventWriter<UiAction>) {
+fn show_submitted(
+    mut ui_ctx: Query<&mut EguiContext>,
+    ctx: Res<Context>,
+    images: Res<EguiUserTextures>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
+) {
+    let mut ui_ctx = ui_ctx.single_mut();
+
+    root_element(ui_ctx.get_mut(), |ui| {
+        ui.label("Draw");
+        countdown(ui, &timer, &clock, time.elapsed());
+        ui.label("Waiting for other players");
+        ui.horizontal(|ui| {
+            for submission in &ctx.submissions {
+                drawing(ui, &images, submission, IMG_HALF_SIZE);
+            }
+        });
+    });
+}
+
+/// Submit the canvas when the timer runs out or the server warns the phase is ending,
+/// if there's a drawing left to submit and something was drawn, even a stroke still in progress.
+fn submit_on_timeout(
+    mut timer: ResMut<PhaseTimer>,
+    mut actions: EventWriter<UiAction>,
+    ctx: Res<Context>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
+) {
+    if timer.take_timeout(&clock, time.elapsed())
+        && ctx.submitted < ctx.drawings
+        && (!ctx.history.edits().is_empty() || ctx.stroke.is_some())
+    {
+        actions.send(UiAction::Submit);
+    }
+}
+
+fn show_brush_colors(ui: &mut egui::Ui, ctx: &Context, actions: &mut EventWriter<UiAction>) {
     ui.vertical(|ui| {
+        for (tool, name) in [(Tool::Brush, "Brush"), (Tool::Bucket, "Bucket")] {
+            if ui.selectable_label(ctx.tool == tool, name).clicked() {
+                actions.send(UiAction::Tool(tool));
+            }
+        }
         let eraser = ui.button("Eraser").clicked();
         if eraser {
             actions.send(UiAction::BrushColor(egui::Color32::TRANSPARENT));
@@ -186,7 +361,7 @@ fn show_brush_colors(ui: &mut egui::Ui, actions: &mut EventWriter<UiAction>) {
         egui::Grid::new("brush-colors")
             .num_columns(2)
             .show(ui, |ui| {
-                for (i, color) in BRUSH_COLORS.into_iter().enumerate() {
+                for (i, color) in BRUSH_COLORS.map(color32).into_iter().enumerate() {
                     let (rect, resp) =
                         ui.allocate_exact_size(egui::Vec2::splat(10.0), egui::Sense::click());
                     let painter = ui.painter_at(rect);
@@ -208,7 +383,7 @@ fn show_shirt_colors(ui: &mut egui::Ui, actions: &mut EventWriter<UiAction>) {
         egui::Grid::new("shirt-colors")
             .num_columns(2)
             .show(ui, |ui| {
-                for (i, color) in BRUSH_COLORS.into_iter().enumerate() {
+                for (i, color) in BRUSH_COLORS.map(color32).into_iter().enumerate() {
                     let (rect, resp) =
                         ui.allocate_exact_size(egui::Vec2::splat(10.0), egui::Sense::click());
                     let painter = ui.painter_at(rect);
@@ -226,18 +401,55 @@ fn show_shirt_colors(ui: &mut egui::Ui, actions: &mut EventWriter<UiAction>) {
 
 fn send_image(
     mut client: ResMut<QuinnetClient>,
-    comm: Res<save_image::MainWorldComm>,
-    ctx: Res<Context>,
+    mut images: ResMut<Assets<Image>>,
+    mut textures: ResMut<EguiUserTextures>,
+    mut ctx: ResMut<Context>,
+    mut next: ResMut<NextState<PhaseState>>,
+    mut actions: EventReader<UiAction>,
 ) {
-    let Some(drawing) = comm.receiver.try_recv().ok() else {
+    // Clicking submit as the timer runs out only sends the drawing once
+    let submit = actions
+        .read()
+        .filter(|action| matches!(action, UiAction::Submit))
+        .count()
+        > 0;
+    if !submit || ctx.submitted >= ctx.drawings {
         return;
-    };
+    }
+    // Edits are rasterised on the CPU, so the result doesn't depend on the GPU
     let bg_color = [ctx.bg_color.r(), ctx.bg_color.g(), ctx.bg_color.b()];
+    let mut edits = ctx.history.edits().to_vec();
+    edits.extend(ctx.stroke.clone().map(Edit::Stroke));
+    let vector = Drawing::from_edits(&edits, bg_color);
+    if vector.is_none() {
+        warn!("Edits don't fit the vector format, sending pixels");
+    }
+    let drawing = vector.unwrap_or_else(|| {
+        let mut canvas = ctx.canvas.clone();
+        if let Some(stroke) = &ctx.stroke {
+            canvas.draw(stroke);
+        }
+        Drawing {
+            drawing: Picture::Pixels(canvas.to_bgra()),
+            bg_color,
+        }
+    });
 
-    client
-        .connection_mut()
-        .send_message(ClientMsgComm::SubmitDrawing(Drawing { drawing, bg_color }).root())
-        .ok();
+    send(
+        &mut client,
+        ClientMsgComm::SubmitDrawing(drawing.clone()).root(),
+    );
+    let submission = prep_drawing(&mut images, &mut textures, drawing);
+    ctx.submissions.push(submission);
+
+    // Start over with a blank canvas
+    ctx.submitted += 1;
+    ctx.stroke = None;
+    ctx.history.clear();
+    ctx.rebuild_canvas();
+    if ctx.submitted >= ctx.drawings {
+        next.set(PhaseState::Submitted);
+    }
 }
 
 fn show_brushes(
@@ -265,13 +477,7 @@ fn show_brushes(
         });
 }
 
-fn show_canvas(
-    ui: &mut egui::Ui,
-    ctx: &mut Context,
-    gizmos: &mut Gizmos<DefaultGizmoConfigGroup, ()>,
-    images: &EguiUserTextures,
-    window: &Window,
-) {
+fn show_canvas(ui: &mut egui::Ui, ctx: &mut Context, images: &EguiUserTextures, window: &Window) {
     let (padded_rect, resp) = ui.allocate_exact_size(
         egui::Vec2::splat(IMG_SIZE + 2.0 * IMG_PADDING),
         egui::Sense::click_and_drag(),
@@ -280,7 +486,9 @@ fn show_canvas(
     let image_id = images.image_id(&ctx.image_handle).unwrap();
     let painter = ui.painter_at(padded_rect);
     painter.rect_filled(padded_rect, 0.0, ctx.bg_color);
-    let paint = resp.is_pointer_button_down_on();
+    // A stroke being drawn is finished even if it fills the history
+    let can_edit = ctx.stroke.is_some() || !ctx.history.is_full();
+    let paint = ctx.tool == Tool::Brush && resp.is_pointer_button_down_on() && can_edit;
 
     let img_rect = padded_rect.shrink(IMG_PADDING);
     ui.allocate_ui_at_rect(img_rect, |ui| {
@@ -293,32 +501,46 @@ fn show_canvas(
 
     let rescaler = Scaler::new(
         img_rect,
-        egui::Rect::from_min_max(
-            egui::pos2(-IMG_HALF_SIZE, IMG_HALF_SIZE),
-            egui::pos2(IMG_HALF_SIZE, -IMG_HALF_SIZE),
-        ),
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(IMG_SIZE, IMG_SIZE)),
     );
 
     if let Some(current_pos) = window.cursor_position() {
-        let color = ctx.brush_color;
-        let color = Color::srgba_u8(color.r(), color.g(), color.b(), color.a());
         if paint {
             let current_pos = rescaler.scale(current_pos);
-            gizmos.ellipse_2d(current_pos, 0.0, Vec2::ONE, color);
-            gizmos.ellipse_2d(current_pos, std::f32::consts::PI, Vec2::ONE, color);
-
-            if let Some(last_pos) = ctx.last_pos {
-                if let Some(dir) = (current_pos - last_pos).try_normalize() {
-                    let side_offset = Vec2::new(dir.y, -dir.x);
-                    gizmos.line_2d(last_pos - side_offset, current_pos - side_offset, color);
-                    gizmos.line_2d(last_pos + side_offset, current_pos + side_offset, color);
-                    ctx.last_pos = Some(current_pos);
+            let points_left = MAX_POINTS.saturating_sub(ctx.history.points());
+            match &mut ctx.stroke {
+                Some(stroke) => {
+                    let last_pos = *stroke.points.last().unwrap();
+                    if last_pos != current_pos && stroke.points.len() < points_left {
+                        stroke.points.push(current_pos);
+                        ctx.preview_stroke_end();
+                    }
+                }
+                None => {
+                    ctx.stroke = Some(Stroke {
+                        points: vec![current_pos],
+                        width: ctx.brush_size,
+                        color: ctx.brush_color.to_array(),
+                        eraser: ctx.brush_color == egui::Color32::TRANSPARENT,
+                    });
+                    ctx.preview_stroke_end();
                 }
-            } else {
-                ctx.last_pos = Some(current_pos);
             }
-        } else if ctx.last_pos.is_some() {
-            ctx.last_pos = None;
+        } else if let Some(stroke) = ctx.stroke.take() {
+            ctx.canvas.draw(&stroke);
+            ctx.history.push(Edit::Stroke(stroke));
+            ctx.reset_preview();
+        }
+
+        if ctx.tool == Tool::Bucket && resp.clicked() && can_edit {
+            let fill = Fill {
+                point: rescaler.scale(current_pos),
+                color: ctx.brush_color.to_array(),
+                tolerance: FILL_TOLERANCE,
+            };
+            ctx.canvas.fill(&fill);
+            ctx.history.push(Edit::Fill(fill));
+            ctx.reset_preview();
         }
 
         let painter = ui.painter_at(padded_rect);
@@ -330,152 +552,134 @@ fn show_canvas(
         painter.circle_stroke(
             current_pos,
             ctx.brush_size / 2.0 + 3.0,
-            Stroke::new(1.0, color),
+            egui::Stroke::new(1.0, color),
         );
     }
 
     ui.advance_cursor_after_rect(padded_rect);
 }
 
-fn execute_actions(
-    mut ctx: ResMut<Context>,
-    mut actions: EventReader<UiAction>,
-    mut gizmo_configs: ResMut<GizmoConfigStore>,
-    comm: ResMut<save_image::MainWorldComm>,
-) {
+fn color32([r, g, b]: [u8; 3]) -> egui::Color32 {
+    egui::Color32::from_rgb(r, g, b)
+}
+
+fn show_history(ui: &mut egui::Ui, ctx: &Context, actions: &mut EventWriter<UiAction>) {
+    // Ctrl+Z undoes, Ctrl+Y and Ctrl+Shift+Z redo
+    let (undo_key, redo_key) = ui.input(|i| {
+        let command = i.modifiers.command;
+        let z = command && i.key_pressed(egui::Key::Z);
+        (
+            z && !i.modifiers.shift,
+            (z && i.modifiers.shift) || (command && i.key_pressed(egui::Key::Y)),
+        )
+    });
+    ui.horizontal(|ui| {
+        let undo = ui.add_enabled(ctx.history.can_undo(), egui::Button::new("Undo"));
+        if undo.clicked() || undo_key {
+            actions.send(UiAction::Undo);
+        }
+        let redo = ui.add_enabled(ctx.history.can_redo(), egui::Button::new("Redo"));
+        if redo.clicked() || redo_key {
+            actions.send(UiAction::Redo);
+        }
+        if ctx.history.is_full() {
+            ui.colored_label(
+                egui::Color32::RED,
+                "Stroke limit reached, undo to keep drawing",
+            );
+        }
+    });
+}
+
+/// Copy the changed part of the preview to the image.
+fn update_image(mut ctx: ResMut<Context>, mut images: ResMut<Assets<Image>>) {
+    let Some(region) = ctx.dirty.take() else {
+        return;
+    };
+    let Some(image) = images.get_mut(&ctx.image_handle) else {
+        return;
+    };
+    ctx.preview.copy_bgra(region, &mut image.data);
+}
+
+fn execute_actions(mut ctx: ResMut<Context>, mut actions: EventReader<UiAction>) {
     for action in actions.read() {
         match action {
-            UiAction::BrushSize(size) => {
-                let config = gizmo_configs.config_mut::<DefaultGizmoConfigGroup>().0;
-                ctx.brush_size = *size;
-                config.line_width = size - 1.0;
-            }
+            UiAction::Tool(tool) => ctx.tool = *tool,
+            UiAction::BrushSize(size) => ctx.brush_size = *size,
             UiAction::BrushColor(color) => ctx.brush_color = *color,
             UiAction::ShirtColor(color) => ctx.bg_color = *color,
-            UiAction::Submit => {
-                comm.sender.try_send(ctx.image_handle.clone()).ok();
+            UiAction::Undo | UiAction::Redo => {
+                let changed = match action {
+                    UiAction::Undo => ctx.history.undo(),
+                    _ => ctx.history.redo(),
+                };
+                if changed {
+                    ctx.rebuild_canvas();
+                }
             }
+            // Drawings are rasterised from the history by send_image
+            UiAction::Submit => {}
         }
     }
 }
 
-mod save_image {
-    use super::IMG_SIZE;
-
-    use bevy::prelude::*;
-    use bevy::{
-        render::{
-            {
-                render_asset::RenderAssets,
-                render_resource::{
-                    BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
-                    ImageCopyBuffer, ImageDataLayout, MapMode,
-                },
-            },
-            {
-                renderer::{RenderDevice, RenderQueue},
-                texture::GpuImage,
-            },
-            {Render, RenderApp},
-        },
-        tasks::AsyncComputeTaskPool,
-    };
-    use crossbeam_channel::{Receiver, Sender};
-
-    pub struct SaveDrawingPlugin;
-
-    impl Plugin for SaveDrawingPlugin {
-        fn build(&self, app: &mut App) {
-            let (m_s, r_r) = crossbeam_channel::unbounded();
-            let (r_s, m_r) = crossbeam_channel::unbounded();
-
-            app.insert_resource(MainWorldComm {
-                receiver: m_r,
-                sender: m_s,
-            });
-
-            let render_app = app.sub_app_mut(RenderApp);
-            render_app.insert_resource(RenderWorldComm {
-                sender: r_s,
-                receiver: r_r,
-            });
-            render_app.add_systems(Render, save_drawing);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(x: f32) -> Edit {
+        Edit::Stroke(Stroke {
+            points: vec![Vec2::new(x, 0.0)],
+            width: BRUSH_SIZES[0],
+            color: egui::Color32::BLACK.to_array(),
+            eraser: false,
+        })
     }
 
-    #[derive(Resource)]
-    pub struct MainWorldComm {
-        pub receiver: Receiver<Vec<u8>>,
-        pub sender: Sender<Handle<Image>>,
+    #[test]
+    fn undo_and_redo_move_edits_between_lists() {
+        let mut history = History::default();
+        assert!(!history.undo());
+        history.push(stroke(1.0));
+        history.push(stroke(2.0));
+        assert!(history.undo());
+        assert_eq!(history.edits(), &[stroke(1.0)]);
+        assert!(history.redo());
+        assert_eq!(history.edits(), &[stroke(1.0), stroke(2.0)]);
+        assert!(!history.redo());
     }
 
-    #[derive(Resource)]
-    pub struct RenderWorldComm {
-        pub sender: Sender<Vec<u8>>,
-        pub receiver: Receiver<Handle<Image>>,
+    #[test]
+    fn new_edit_drops_undone_ones() {
+        let mut history = History::default();
+        history.push(stroke(1.0));
+        history.undo();
+        assert!(history.can_redo());
+        history.push(stroke(2.0));
+        assert!(!history.can_redo());
+        assert_eq!(history.edits(), &[stroke(2.0)]);
     }
 
-    pub(crate) fn save_drawing(
-        device: Res<RenderDevice>,
-        queue: Res<RenderQueue>,
-        assets: Res<RenderAssets<GpuImage>>,
-        comm: Res<RenderWorldComm>,
-    ) {
-        // Wait for request
-        let Some(handle) = comm.receiver.try_recv().ok() else {
-            return;
-        };
-
-        // Prepare destination buffer, hardcoded image size of 512x512 in Bgra8UnormSrgb
-        let image = assets.get(&handle).expect("Missing asset");
-        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
-        let buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("drawing-transfer-buffer"),
-            size: IMG_SIZE as u64 * IMG_SIZE as u64 * 4,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        // Request copy
-        encoder.copy_texture_to_buffer(
-            image.texture.as_image_copy(),
-            ImageCopyBuffer {
-                buffer: &buffer,
-                layout: ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(IMG_SIZE as u32 * 4),
-                    rows_per_image: None,
-                },
-            },
-            Extent3d {
-                width: image.size.x,
-                height: image.size.y,
-                ..default()
-            },
-        );
-        queue.submit([encoder.finish()]);
-
-        // Wait for copy to finish
-        let sender = comm.sender.clone();
-        let finish = async move {
-            let (tx, rx) = async_channel::bounded(1);
-            // Copy data from GPU to CPU
-            let buffer_slice = buffer.slice(..);
-            buffer_slice.map_async(MapMode::Read, move |result| {
-                let err = result.err();
-                if err.is_some() {
-                    panic!("{}", err.unwrap().to_string());
-                }
-                tx.try_send(()).unwrap();
-            });
-            rx.recv().await.unwrap();
-            let data = buffer_slice.get_mapped_range();
-            let result = Vec::from(&*data);
-            drop(data);
-            drop(buffer);
-            sender.send(result).ok();
-        };
-
-        AsyncComputeTaskPool::get().spawn(finish).detach();
+    #[test]
+    fn history_fills_up_at_the_stroke_limit() {
+        let mut history = History::default();
+        for _ in 1..MAX_STROKES {
+            history.push(stroke(1.0));
+        }
+        assert!(!history.is_full());
+        history.push(stroke(1.0));
+        assert!(history.is_full());
+        history.undo();
+        assert!(!history.is_full());
+
+        history.clear();
+        history.push(Edit::Stroke(Stroke {
+            points: vec![Vec2::ZERO; MAX_POINTS],
+            width: BRUSH_SIZES[0],
+            color: egui::Color32::BLACK.to_array(),
+            eraser: false,
+        }));
+        assert!(history.is_full());
     }
 }
@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 use bevy::prelude::*;
 use bevy_egui::EguiContext;
 use bevy_quinnet::client::QuinnetClient;
@@ -11,10 +9,12 @@ use common::{
 use rand::Rng;
 
 use crate::{
-    states::GameState,
+    clock::{PhaseTimer, ServerClock},
+    networking::send,
+    states::{GameState, PhaseState},
     ui::{
         fonts::{IntoFontFamily, FONTS},
-        widgets::root_element,
+        widgets::{countdown, root_element},
     },
     GameSystemOdering,
 };
@@ -28,7 +28,15 @@ impl Plugin for ModePlugin {
             GameState::Prompt,
             setup,
             teardown,
-            (show_ui, execute_actions)
+            (
+                take_rejections,
+                (
+                    (submit_on_timeout, show_ui, execute_actions)
+                        .chain()
+                        .run_if(in_state(PhaseState::Working)),
+                    show_submitted.run_if(in_state(PhaseState::Submitted)),
+                ),
+            )
                 .chain()
                 .in_set(GameSystemOdering::StateLogic),
         );
@@ -37,23 +45,37 @@ impl Plugin for ModePlugin {
 
 #[derive(Resource, Clone)]
 pub struct Data {
-    pub duration: Duration,
+    pub prompts: usize,
 }
 
+/// Prompts the server refused, which don't count as submitted.
+#[derive(Resource, Debug, Default)]
+pub struct Rejected(pub Vec<Prompt>);
+
 #[derive(Resource)]
 pub struct Context {
     pub font: CustomFont,
     pub prompt: String,
+    pub prompts: usize,
+    pub submitted: usize,
+    /// Prompts submitted so far, to show while waiting for other players.
+    pub submissions: Vec<Prompt>,
+    /// Whether a submitted prompt was refused, so another one has to be written.
+    pub refused: bool,
 }
 
 impl Context {
-    pub fn new() -> Self {
+    pub fn new(prompts: usize, submitted: usize) -> Self {
         let mut rng = rand::thread_rng();
         let font = CustomFont(rng.gen_range(0..FONTS.len()));
 
         Context {
             font,
             prompt: String::new(),
+            prompts,
+            submitted,
+            submissions: vec![],
+            refused: false,
         }
     }
 }
@@ -63,20 +85,25 @@ enum UiAction {
     Submit,
 }
 
-fn setup(mut commands: Commands, mut actions: ResMut<Events<UiAction>>) {
+fn setup(mut commands: Commands, mut actions: ResMut<Events<UiAction>>, data: Res<Data>) {
     actions.clear();
 
-    commands.insert_resource(Context::new());
+    commands.insert_resource(Context::new(data.prompts, 0));
+    commands.remove_resource::<Rejected>();
 }
 
 fn show_ui(
     mut ui_ctx: Query<&mut EguiContext>,
     mut ctx: ResMut<Context>,
     mut actions: EventWriter<UiAction>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
 ) {
     let mut ui_ctx = ui_ctx.single_mut();
 
     root_element(ui_ctx.get_mut(), |ui| {
+        countdown(ui, &timer, &clock, time.elapsed());
         ui.horizontal(|ui| {
             let font_id = ctx.font.get_font_id();
             ui.label(egui::RichText::new("Prompt").font(font_id.clone()));
@@ -87,32 +114,102 @@ fn show_ui(
             );
         });
 
-        if ui.button("Submit").clicked() {
+        if ctx.refused {
+            ui.colored_label(
+                egui::Color32::LIGHT_RED,
+                "A prompt was refused, write another one",
+            );
+        }
+        ui.label(format!("{} of {} submitted", ctx.submitted, ctx.prompts));
+        let can_submit = ctx.submitted < ctx.prompts;
+        if ui
+            .add_enabled(can_submit, egui::Button::new("Submit"))
+            .clicked()
+        {
             actions.send(UiAction::Submit);
         }
     });
 }
 
+fn show_submitted(
+    mut ui_ctx: Query<&mut EguiContext>,
+    ctx: Res<Context>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
+) {
+    let mut ui_ctx = ui_ctx.single_mut();
+
+    root_element(ui_ctx.get_mut(), |ui| {
+        countdown(ui, &timer, &clock, time.elapsed());
+        ui.label("Waiting for other players");
+        for prompt in &ctx.submissions {
+            ui.label(
+                egui::RichText::new(&prompt.text)
+                    .font(prompt.font.get_font_id())
+                    .color(egui::Color32::WHITE),
+            );
+        }
+    });
+}
+
+/// Submit the prompt being written when the timer runs out or the server warns the phase is
+/// ending.
+fn submit_on_timeout(
+    mut timer: ResMut<PhaseTimer>,
+    mut actions: EventWriter<UiAction>,
+    ctx: Res<Context>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
+) {
+    if timer.take_timeout(&clock, time.elapsed())
+        && ctx.submitted < ctx.prompts
+        && !ctx.prompt.trim().is_empty()
+    {
+        actions.send(UiAction::Submit);
+    }
+}
+
+/// Give back the submissions the server refused, so the player can write new prompts.
+fn take_rejections(
+    rejected: Option<ResMut<Rejected>>,
+    mut ctx: ResMut<Context>,
+    mut next: ResMut<NextState<PhaseState>>,
+) {
+    let Some(mut rejected) = rejected else {
+        return;
+    };
+    for prompt in rejected.0.drain(..) {
+        if let Some(i) = ctx.submissions.iter().position(|p| p.text == prompt.text) {
+            ctx.submissions.remove(i);
+        }
+        ctx.submitted = ctx.submitted.saturating_sub(1);
+        ctx.refused = true;
+        next.set(PhaseState::Working);
+    }
+}
+
 fn execute_actions(
     mut actions: ResMut<Events<UiAction>>,
     mut ctx: ResMut<Context>,
     mut client: ResMut<QuinnetClient>,
+    mut next: ResMut<NextState<PhaseState>>,
 ) {
     for action in actions.drain() {
         match action {
             UiAction::Submit => {
-                let mut old_ctx = Context::new();
+                let mut old_ctx = Context::new(ctx.prompts, ctx.submitted + 1);
                 std::mem::swap(&mut *ctx, &mut old_ctx);
-                client
-                    .connection_mut()
-                    .send_message(
-                        ClientMsgComm::SubmitPrompt(Prompt {
-                            text: old_ctx.prompt,
-                            font: old_ctx.font,
-                        })
-                        .root(),
-                    )
-                    .ok();
+                let prompt = Prompt {
+                    text: old_ctx.prompt,
+                    font: old_ctx.font,
+                };
+                ctx.submissions = old_ctx.submissions;
+                ctx.submissions.push(prompt.clone());
+                send(&mut client, ClientMsgComm::SubmitPrompt(prompt).root());
+                if ctx.submitted >= ctx.prompts {
+                    next.set(PhaseState::Submitted);
+                }
             }
         }
     }
@@ -121,5 +218,6 @@ fn execute_actions(
 fn teardown(mut commands: Commands, mut actions: ResMut<Events<UiAction>>) {
     commands.remove_resource::<Data>();
     commands.remove_resource::<Context>();
+    commands.remove_resource::<Rejected>();
     actions.clear();
 }
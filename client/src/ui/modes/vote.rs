@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 use bevy::{
     prelude::*,
     render::render_resource::{
@@ -10,14 +8,19 @@ use bevy_egui::{EguiContext, EguiUserTextures};
 use bevy_quinnet::client::QuinnetClient;
 use common::{
     app::AppExt,
-    game::{Drawing, Index, Prompt, Vote},
+    game::{Drawing, Index, Prompt, Vote, VoteTally, IMG_SIZE},
     protocol::ClientMsgComm,
 };
 use egui::RichText;
 
 use crate::{
+    clock::{PhaseTimer, ServerClock},
+    networking::send,
     states::GameState,
-    ui::{fonts::IntoFontFamily, widgets::root_element},
+    ui::{
+        fonts::IntoFontFamily,
+        widgets::{countdown, root_element},
+    },
     GameSystemOdering,
 };
 
@@ -39,19 +42,23 @@ impl Plugin for ModePlugin {
 
 #[derive(Resource, Clone)]
 pub struct Data {
-    pub duration: Duration,
     pub combination1: (Index, Drawing, Prompt),
     pub combination2: (Index, Drawing, Prompt),
 }
 
 #[derive(Resource)]
 pub struct Context {
-    pub duration: Duration,
     pub combination1: (Index, (Handle<Image>, egui::Color32), Prompt),
     pub combination2: (Index, (Handle<Image>, egui::Color32), Prompt),
     pub shirt: Handle<Image>,
+    /// Combination the player last voted for.
+    pub selected: Option<Index>,
 }
 
+/// Votes cast so far, if the room has live tallies.
+#[derive(Resource, Debug)]
+pub struct Tally(pub VoteTally);
+
 #[derive(Event)]
 enum UiAction {
     Vote1,
@@ -76,10 +83,10 @@ fn setup(
     let combination2 = prep_combination(&mut images, &mut egui_user_textures, data.combination2);
 
     commands.insert_resource(Context {
-        duration: data.duration,
         combination1,
         combination2,
         shirt,
+        selected: None,
     });
 }
 
@@ -88,9 +95,26 @@ fn prep_combination(
     egui_user_textures: &mut EguiUserTextures,
     combination: (Index, Drawing, Prompt),
 ) -> (Index, (Handle<Image>, egui::Color32), Prompt) {
+    let drawing = prep_drawing(images, egui_user_textures, combination.1);
+    (combination.0, drawing, combination.2)
+}
+
+/// Upload a drawing for [`show_combination`].
+pub fn prep_drawing(
+    images: &mut Assets<Image>,
+    egui_user_textures: &mut EguiUserTextures,
+    drawing: Drawing,
+) -> (Handle<Image>, egui::Color32) {
+    let bg_color = drawing.bg_color;
+    let bg_color = egui::Color32::from_rgb(bg_color[0], bg_color[1], bg_color[2]);
+    // The server checks strokes decode, so a blank drawing is only a fallback
+    let pixels = drawing.into_pixels().unwrap_or_else(|err| {
+        warn!(?err, "Invalid drawing");
+        vec![0; IMG_SIZE * IMG_SIZE * 4]
+    });
     let size = Extent3d {
-        width: 512,
-        height: 512,
+        width: IMG_SIZE as u32,
+        height: IMG_SIZE as u32,
         ..default()
     };
     let image = Image {
@@ -106,14 +130,12 @@ fn prep_combination(
                 | TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[],
         },
-        data: combination.1.drawing,
+        data: pixels,
         ..default()
     };
     let image_handle = images.add(image);
     egui_user_textures.add_image(image_handle.clone_weak());
-    let bg_color = combination.1.bg_color;
-    let bg_color = egui::Color32::from_rgb(bg_color[0], bg_color[1], bg_color[2]);
-    (combination.0, (image_handle, bg_color), combination.2)
+    (image_handle, bg_color)
 }
 
 fn show_ui(
@@ -121,41 +143,75 @@ fn show_ui(
     mut actions: EventWriter<UiAction>,
     images: Res<EguiUserTextures>,
     ctx: Res<Context>,
+    tally: Option<Res<Tally>>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
 ) {
     let mut ui_ctx = ui_ctx.single_mut();
 
     root_element(ui_ctx.get_mut(), |ui| {
         ui.label("Vote");
+        countdown(ui, &timer, &clock, time.elapsed());
+        let tally = tally.map(|tally| tally.0.clone());
+        if let Some(tally) = &tally {
+            show_progress(ui, tally);
+        }
+        // Votes for each side, if the room shows them
+        let votes = match tally.and_then(|tally| tally.votes) {
+            Some((votes1, votes2)) => [Some(votes1), Some(votes2)],
+            None => [None, None],
+        };
 
         ui.horizontal(|ui| {
-            ui.vertical(|ui| {
-                show_combination(
-                    ui,
-                    &images,
-                    &ctx.combination1.1,
-                    &ctx.combination1.2,
-                    &ctx.shirt,
-                );
-                if ui.button("Vote").clicked() {
-                    actions.send(UiAction::Vote1);
-                }
-            });
-            ui.vertical(|ui| {
-                show_combination(
-                    ui,
-                    &images,
-                    &ctx.combination2.1,
-                    &ctx.combination2.2,
-                    &ctx.shirt,
-                );
-                if ui.button("Vote").clicked() {
-                    actions.send(UiAction::Vote2);
-                }
-            });
+            let sides = [
+                (&ctx.combination1, UiAction::Vote1),
+                (&ctx.combination2, UiAction::Vote2),
+            ];
+            for ((combination, action), votes) in sides.into_iter().zip(votes) {
+                let selected = ctx.selected == Some(combination.0);
+                highlight(ui, selected, |ui| {
+                    show_combination(ui, &images, &combination.1, &combination.2, &ctx.shirt);
+                    if let Some(votes) = votes {
+                        ui.label(format!("{votes} votes"));
+                    }
+                    let text = if selected { "Voted" } else { "Vote" };
+                    if ui.button(text).clicked() {
+                        actions.send(action);
+                    }
+                });
+            }
         });
     });
 }
 
+/// How many players voted so far.
+fn show_progress(ui: &mut egui::Ui, tally: &VoteTally) {
+    let fraction = if tally.players == 0 {
+        0.0
+    } else {
+        tally.voted as f32 / tally.players as f32
+    };
+    ui.add(
+        egui::ProgressBar::new(fraction)
+            .desired_width(256.0)
+            .text(format!("{}/{} voted", tally.voted, tally.players)),
+    );
+}
+
+/// Frame around the combination the player voted for.
+fn highlight(ui: &mut egui::Ui, selected: bool, add_contents: impl FnOnce(&mut egui::Ui)) {
+    let stroke = if selected {
+        egui::Stroke::new(4.0, egui::Color32::GOLD)
+    } else {
+        egui::Stroke::NONE
+    };
+    egui::Frame::none()
+        .stroke(stroke)
+        .inner_margin(4.0)
+        .show(ui, |ui| ui.vertical(add_contents));
+}
+
 pub fn show_combination(
     ui: &mut egui::Ui,
     images: &EguiUserTextures,
@@ -207,38 +263,25 @@ pub fn show_combination(
 fn execute_actions(
     mut actions: ResMut<Events<UiAction>>,
     mut client: ResMut<QuinnetClient>,
-    ctx: ResMut<Context>,
+    mut ctx: ResMut<Context>,
 ) {
     for action in actions.drain() {
-        match action {
-            UiAction::Vote1 => {
-                client
-                    .connection_mut()
-                    .send_message(
-                        ClientMsgComm::SubmitVote(Vote {
-                            combination: ctx.combination2.0,
-                        })
-                        .root(),
-                    )
-                    .ok();
-            }
-            UiAction::Vote2 => {
-                client
-                    .connection_mut()
-                    .send_message(
-                        ClientMsgComm::SubmitVote(Vote {
-                            combination: ctx.combination2.0,
-                        })
-                        .root(),
-                    )
-                    .ok();
-            }
-        }
+        let combination = match action {
+            UiAction::Vote1 => ctx.combination1.0,
+            UiAction::Vote2 => ctx.combination2.0,
+        };
+        // Votes can change until the deadline, the server keeps the latest one
+        ctx.selected = Some(combination);
+        send(
+            &mut client,
+            ClientMsgComm::SubmitVote(Vote { combination }).root(),
+        );
     }
 }
 
 fn teardown(mut commands: Commands, mut actions: ResMut<Events<UiAction>>) {
     commands.remove_resource::<Data>();
     commands.remove_resource::<Context>();
+    commands.remove_resource::<Tally>();
     actions.clear();
 }
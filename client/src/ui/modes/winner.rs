@@ -1,18 +1,19 @@
-use std::time::Duration;
-
-use bevy::{
-    prelude::*,
-    render::render_resource::{
-        Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    },
-};
+use bevy::prelude::*;
 use bevy_egui::{EguiContext, EguiUserTextures};
 use common::{
     app::AppExt,
-    game::{Drawing, Prompt},
+    game::{RevealStage, VoteResult},
 };
 
-use crate::{states::GameState, ui::widgets::root_element, GameSystemOdering};
+use crate::{
+    clock::{PhaseTimer, ServerClock},
+    states::GameState,
+    ui::{
+        modes::vote::{prep_drawing, show_combination},
+        widgets::{countdown, root_element},
+    },
+    GameSystemOdering,
+};
 
 pub struct ModePlugin;
 
@@ -29,17 +30,27 @@ impl Plugin for ModePlugin {
 
 #[derive(Resource, Clone)]
 pub struct Data {
-    pub duration: Duration,
-    pub drawing: Drawing,
-    pub prompt: Prompt,
+    pub winner: VoteResult,
+    pub loser: VoteResult,
+    pub points: u32,
 }
 
+/// Latest part of the result the server revealed.
+#[derive(Resource, Debug)]
+pub struct Revealed(pub RevealStage);
+
 #[derive(Resource)]
 pub struct Context {
-    pub duration: Duration,
-    pub drawing: (Handle<Image>, egui::Color32),
-    pub prompt: Prompt,
+    pub winner: Side,
+    pub loser: Side,
     pub shirt: Handle<Image>,
+    pub points: u32,
+}
+
+/// Combination on one side of the result.
+pub struct Side {
+    pub drawing: (Handle<Image>, egui::Color32),
+    pub result: VoteResult,
 }
 
 fn setup(
@@ -54,53 +65,68 @@ fn setup(
     let shirt: Handle<Image> = asset_server.load("textures/shirts/shirt1.png"); // TODO: cache loaded images
     egui_user_textures.add_image(shirt.clone_weak());
 
-    let size = Extent3d {
-        width: 512,
-        height: 512,
-        ..default()
-    };
-    let image = Image {
-        texture_descriptor: TextureDescriptor {
-            label: None,
-            size,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Bgra8UnormSrgb,
-            mip_level_count: 1,
-            sample_count: 1,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_DST
-                | TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
-        },
-        data: data.drawing.drawing,
-        ..default()
+    let mut side = |result: VoteResult| Side {
+        drawing: prep_drawing(&mut images, &mut egui_user_textures, result.drawing.clone()),
+        result,
     };
-    let image_handle = images.add(image);
-    egui_user_textures.add_image(image_handle.clone_weak());
-    let bg_color = data.drawing.bg_color;
-    let bg_color = egui::Color32::from_rgb(bg_color[0], bg_color[1], bg_color[2]);
-
     commands.insert_resource(Context {
-        duration: data.duration,
-        drawing: (image_handle, bg_color),
-        prompt: data.prompt,
+        winner: side(data.winner),
+        loser: side(data.loser),
         shirt,
+        points: data.points,
     });
 }
 
-fn show_ui(mut ui_ctx: Query<&mut EguiContext>, images: Res<EguiUserTextures>, ctx: Res<Context>) {
+fn show_ui(
+    mut ui_ctx: Query<&mut EguiContext>,
+    images: Res<EguiUserTextures>,
+    ctx: Res<Context>,
+    revealed: Option<Res<Revealed>>,
+    timer: Res<PhaseTimer>,
+    clock: Res<ServerClock>,
+    time: Res<Time<Real>>,
+) {
     let mut ui_ctx = ui_ctx.single_mut();
+    let revealed = |stage| revealed.as_ref().is_some_and(|r| r.0 >= stage);
 
     root_element(ui_ctx.get_mut(), |ui| {
         ui.label("Winner");
+        countdown(ui, &timer, &clock, time.elapsed());
 
-        ui.vertical(|ui| {
-            super::vote::show_combination(ui, &images, &ctx.drawing, &ctx.prompt, &ctx.shirt);
+        ui.horizontal(|ui| {
+            for (title, side) in [("Winner", &ctx.winner), ("Runner-up", &ctx.loser)] {
+                ui.vertical(|ui| {
+                    ui.heading(title);
+                    show_combination(ui, &images, &side.drawing, &side.result.prompt, &ctx.shirt);
+                    show_result(ui, &side.result, revealed);
+                });
+            }
         });
+        if revealed(RevealStage::Score) {
+            ui.label(format!("+{} points", ctx.points));
+        }
     });
 }
 
+/// Parts of a side's result revealed so far.
+fn show_result(ui: &mut egui::Ui, result: &VoteResult, revealed: impl Fn(RevealStage) -> bool) {
+    if revealed(RevealStage::Votes) {
+        ui.label(format!("{} votes", result.votes));
+        if !result.voters.is_empty() {
+            ui.label(result.voters.join(", "));
+        }
+    }
+    if revealed(RevealStage::Authors) {
+        let credits = &result.credits;
+        ui.label(format!(
+            "Drawn by {}, written by {}, combined by {}",
+            credits.artist, credits.writer, credits.combiner
+        ));
+    }
+}
+
 fn teardown(mut commands: Commands) {
     commands.remove_resource::<Data>();
     commands.remove_resource::<Context>();
+    commands.remove_resource::<Revealed>();
 }
@@ -0,0 +1,27 @@
+//! Sidebar of who's done with the current phase.
+
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use common::game::SubmissionProgress;
+
+/// Latest progress of the current phase, until the next phase starts.
+#[derive(Resource, Debug)]
+pub struct Progress(pub SubmissionProgress);
+
+pub fn show_sidebar(mut ui_ctx: Query<&mut EguiContext>, progress: Res<Progress>) {
+    let mut ui_ctx = ui_ctx.single_mut();
+
+    egui::SidePanel::right("progress")
+        .resizable(false)
+        .show(ui_ctx.get_mut(), |ui| {
+            ui.heading("Done");
+            for name in &progress.0.done {
+                ui.label(name);
+            }
+            ui.separator();
+            ui.heading("Waiting for");
+            for name in &progress.0.waiting {
+                ui.label(name);
+            }
+        });
+}
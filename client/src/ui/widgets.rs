@@ -1,3 +1,10 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::EguiUserTextures;
+
+use crate::clock::{PhaseTimer, ServerClock};
+
 pub fn root_element<R>(
     ui: &mut egui::Context,
     add_contents: impl FnOnce(&mut egui::Ui) -> R,
@@ -18,3 +25,49 @@ pub fn validated_singleline_textbox(
         egui::Color32::RED
     }))
 }
+
+/// Bar emptying as the current phase runs out of time.
+pub fn countdown(
+    ui: &mut egui::Ui,
+    timer: &PhaseTimer,
+    clock: &ServerClock,
+    now: Duration,
+) -> egui::Response {
+    let remaining = timer.remaining(clock, now);
+    let duration = timer.deadline.duration;
+    let fraction = if duration.is_zero() {
+        0.0
+    } else {
+        remaining.as_secs_f32() / duration.as_secs_f32()
+    };
+    let text = if timer.paused_at.is_some() {
+        "Paused".to_owned()
+    } else {
+        format!("{}s", remaining.as_secs_f32().ceil())
+    };
+    ui.add(
+        egui::ProgressBar::new(fraction)
+            .desired_width(256.0)
+            .text(text),
+    )
+}
+
+/// Drawing on its shirt color.
+pub fn drawing(
+    ui: &mut egui::Ui,
+    images: &EguiUserTextures,
+    drawing: &(Handle<Image>, egui::Color32),
+    size: f32,
+) -> egui::Response {
+    let image_id = images.image_id(&drawing.0).unwrap();
+    let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(size), egui::Sense::hover());
+    ui.painter_at(rect).rect_filled(rect, 0.0, drawing.1);
+    ui.put(
+        rect,
+        egui::Image::from_texture(egui::load::SizedTexture::new(
+            image_id,
+            egui::Vec2::splat(size),
+        )),
+    );
+    response
+}
@@ -0,0 +1,433 @@
+//! Software rasteriser turning brush strokes into [`Drawing`](crate::game::Drawing) pixels.
+
+use bevy::math::Vec2;
+
+use crate::game::IMG_SIZE;
+
+/// Line drawn in a single press, in pixel coordinates with the origin at the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub points: Vec<Vec2>,
+    /// Diameter of the brush.
+    pub width: f32,
+    /// Premultiplied RGBA.
+    pub color: [u8; 4],
+    pub eraser: bool,
+}
+
+/// Paint bucket fill of the region around a point, in pixel coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub point: Vec2,
+    /// Premultiplied RGBA.
+    pub color: [u8; 4],
+    /// Largest difference of any channel from the colour under the point that still gets filled.
+    pub tolerance: u8,
+}
+
+/// Change made to a canvas.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Stroke(Stroke),
+    Fill(Fill),
+}
+
+/// Rectangle of pixels, from `min` included to `max` excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+}
+
+impl Region {
+    /// Whole canvas.
+    pub const FULL: Self = Self {
+        min: (0, 0),
+        max: (IMG_SIZE, IMG_SIZE),
+    };
+
+    /// Smallest region covering both.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+}
+
+/// Pixels of a drawing in premultiplied RGBA, row by row from the top left.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Canvas {
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self {
+            pixels: vec![[0; 4]; IMG_SIZE * IMG_SIZE],
+        }
+    }
+}
+
+impl Canvas {
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * IMG_SIZE + x]
+    }
+
+    /// Paint a stroke over the canvas, with anti-aliased edges and round caps and joins.
+    ///
+    /// Eraser strokes clear what they cover back to transparent instead. Returns the pixels the
+    /// stroke can have changed.
+    pub fn draw(&mut self, stroke: &Stroke) -> Option<Region> {
+        let mask = Mask::new(stroke)?;
+        let color = stroke.color.map(|c| c as f32);
+        for (y, row) in mask.coverage.chunks(mask.width).enumerate() {
+            for (x, coverage) in row.iter().enumerate() {
+                if *coverage <= 0.0 {
+                    continue;
+                }
+                let pixel = &mut self.pixels[(mask.min.1 + y) * IMG_SIZE + mask.min.0 + x];
+                if stroke.eraser {
+                    for channel in pixel.iter_mut() {
+                        *channel = (*channel as f32 * (1.0 - coverage)).round() as u8;
+                    }
+                    continue;
+                }
+                let keep = 1.0 - color[3] / 255.0 * coverage;
+                for (channel, source) in pixel.iter_mut().zip(color) {
+                    *channel = (source * coverage + *channel as f32 * keep).round() as u8;
+                }
+            }
+        }
+        let height = mask.coverage.len() / mask.width;
+        Some(Region {
+            min: mask.min,
+            max: (mask.min.0 + mask.width, mask.min.1 + height),
+        })
+    }
+
+    /// Replace the colour of the region connected to a point that is similar to the colour under it.
+    pub fn fill(&mut self, fill: &Fill) {
+        if fill.point.cmplt(Vec2::ZERO).any()
+            || fill.point.cmpge(Vec2::splat(IMG_SIZE as f32)).any()
+        {
+            return;
+        }
+        let target = self.pixel(fill.point.x as usize, fill.point.y as usize);
+        let similar = |pixel: [u8; 4]| {
+            pixel
+                .iter()
+                .zip(target)
+                .all(|(channel, target)| channel.abs_diff(target) <= fill.tolerance)
+        };
+        let mut visited = vec![false; self.pixels.len()];
+        let mut pending = vec![(fill.point.x as usize, fill.point.y as usize)];
+        while let Some((x, y)) = pending.pop() {
+            let i = y * IMG_SIZE + x;
+            if visited[i] || !similar(self.pixels[i]) {
+                continue;
+            }
+            visited[i] = true;
+            self.pixels[i] = fill.color;
+            if x > 0 {
+                pending.push((x - 1, y));
+            }
+            if x + 1 < IMG_SIZE {
+                pending.push((x + 1, y));
+            }
+            if y > 0 {
+                pending.push((x, y - 1));
+            }
+            if y + 1 < IMG_SIZE {
+                pending.push((x, y + 1));
+            }
+        }
+    }
+
+    pub fn apply(&mut self, edit: &Edit) {
+        match edit {
+            Edit::Stroke(stroke) => {
+                self.draw(stroke);
+            }
+            Edit::Fill(fill) => self.fill(fill),
+        }
+    }
+
+    /// Pixels in the Bgra8 layout of a [`Drawing`](crate::game::Drawing).
+    pub fn to_bgra(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|[r, g, b, a]| [*b, *g, *r, *a])
+            .collect()
+    }
+
+    /// Copy part of the canvas into pixels laid out like [`Canvas::to_bgra`].
+    pub fn copy_bgra(&self, region: Region, data: &mut [u8]) {
+        for y in region.min.1..region.max.1 {
+            let row = y * IMG_SIZE;
+            let pixels = &self.pixels[row + region.min.0..row + region.max.0];
+            let bytes = &mut data[(row + region.min.0) * 4..(row + region.max.0) * 4];
+            for ([r, g, b, a], out) in pixels.iter().zip(bytes.chunks_exact_mut(4)) {
+                out.copy_from_slice(&[*b, *g, *r, *a]);
+            }
+        }
+    }
+}
+
+/// Apply edits in order on a blank canvas, in the Bgra8 layout of a
+/// [`Drawing`](crate::game::Drawing).
+pub fn rasterize(edits: &[Edit]) -> Vec<u8> {
+    let mut canvas = Canvas::default();
+    for edit in edits {
+        canvas.apply(edit);
+    }
+    canvas.to_bgra()
+}
+
+/// Part of a stroke covering each pixel of its bounding box.
+///
+/// Taking the highest coverage of every segment makes the joins round and keeps overlaps of a
+/// single stroke from blending twice.
+struct Mask {
+    min: (usize, usize),
+    width: usize,
+    coverage: Vec<f32>,
+}
+
+impl Mask {
+    fn new(stroke: &Stroke) -> Option<Self> {
+        let radius = stroke.width / 2.0;
+        // Pixels further than half a pixel out of the brush aren't touched
+        let reach = radius + 0.5;
+        let (lowest, highest) = stroke.points.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(lowest, highest), point| (lowest.min(*point), highest.max(*point)),
+        );
+        let min = clamp_pixel(lowest - reach);
+        let max = clamp_pixel(highest + reach + 1.0);
+        if min.0 >= max.0 || min.1 >= max.1 {
+            return None;
+        }
+        let width = max.0 - min.0;
+        let mut coverage = vec![0.0_f32; width * (max.1 - min.1)];
+
+        let segments = stroke
+            .points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain((stroke.points.len() == 1).then(|| (stroke.points[0], stroke.points[0])));
+        for (from, to) in segments {
+            let start = clamp_pixel(from.min(to) - reach);
+            let end = clamp_pixel(from.max(to) + reach + 1.0);
+            for y in start.1..end.1 {
+                for x in start.0..end.0 {
+                    let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    let distance = distance_to_segment(center, from, to);
+                    let value = (reach - distance).clamp(0.0, 1.0);
+                    let cell = &mut coverage[(y - min.1) * width + x - min.0];
+                    *cell = cell.max(value);
+                }
+            }
+        }
+        Some(Self {
+            min,
+            width,
+            coverage,
+        })
+    }
+}
+
+/// Pixel containing a point, kept within the canvas bounds.
+fn clamp_pixel(point: Vec2) -> (usize, usize) {
+    let clamp = |v: f32| v.clamp(0.0, IMG_SIZE as f32) as usize;
+    (clamp(point.x), clamp(point.y))
+}
+
+fn distance_to_segment(point: Vec2, from: Vec2, to: Vec2) -> f32 {
+    let direction = to - from;
+    let length_sq = direction.length_squared();
+    let t = if length_sq > 0.0 {
+        ((point - from).dot(direction) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(from + direction * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    fn stroke(points: &[(f32, f32)], width: f32, color: [u8; 4]) -> Stroke {
+        Stroke {
+            points: points.iter().map(|(x, y)| Vec2::new(*x, *y)).collect(),
+            width,
+            color,
+            eraser: false,
+        }
+    }
+
+    fn alpha(canvas: &Canvas, x: usize, y: usize) -> u8 {
+        canvas.pixel(x, y)[3]
+    }
+
+    #[test]
+    fn dot_is_round_and_anti_aliased() {
+        let mut canvas = Canvas::default();
+        canvas.draw(&stroke(&[(100.0, 100.0)], 10.0, BLACK));
+        assert_eq!(canvas.pixel(100, 100), BLACK);
+        assert_eq!(alpha(&canvas, 103, 100), 255);
+        // Edge of the brush is partly covered
+        let edge = alpha(&canvas, 104, 100);
+        assert!(0 < edge && edge < 255, "{edge}");
+        // Corners of the bounding square are outside of the circle
+        assert_eq!(alpha(&canvas, 104, 104), 0);
+        assert_eq!(alpha(&canvas, 110, 100), 0);
+    }
+
+    #[test]
+    fn line_has_round_caps() {
+        let mut canvas = Canvas::default();
+        canvas.draw(&stroke(&[(50.0, 50.0), (150.0, 50.0)], 20.0, BLACK));
+        assert_eq!(alpha(&canvas, 100, 45), 255);
+        assert_eq!(alpha(&canvas, 100, 65), 0);
+        // Caps stick out by the radius past the ends, but not in a square
+        assert_eq!(alpha(&canvas, 155, 50), 255);
+        assert_eq!(alpha(&canvas, 158, 58), 0);
+        assert_eq!(alpha(&canvas, 165, 50), 0);
+    }
+
+    #[test]
+    fn stroke_overlapping_itself_blends_once() {
+        let half_red = [128, 0, 0, 128];
+        let mut canvas = Canvas::default();
+        canvas.draw(&stroke(
+            &[(50.0, 50.0), (150.0, 50.0), (50.0, 50.0)],
+            10.0,
+            half_red,
+        ));
+        assert_eq!(canvas.pixel(100, 50), half_red);
+        assert_eq!(canvas.pixel(150, 50), half_red);
+
+        // Separate strokes do blend
+        canvas.draw(&stroke(&[(100.0, 50.0)], 10.0, half_red));
+        assert_eq!(canvas.pixel(100, 50), [192, 0, 0, 192]);
+    }
+
+    #[test]
+    fn eraser_clears_to_transparent() {
+        let mut canvas = Canvas::default();
+        canvas.draw(&stroke(&[(50.0, 50.0), (150.0, 50.0)], 20.0, BLACK));
+        canvas.draw(&Stroke {
+            eraser: true,
+            ..stroke(&[(100.3, 0.0), (100.3, 100.0)], 10.0, [0; 4])
+        });
+        assert_eq!(canvas.pixel(100, 50), [0; 4]);
+        assert_eq!(canvas.pixel(60, 50), BLACK);
+        // Edges fade out
+        let edge = alpha(&canvas, 105, 50);
+        assert!(0 < edge && edge < 255, "{edge}");
+    }
+
+    fn fill(x: f32, y: f32, color: [u8; 4], tolerance: u8) -> Fill {
+        Fill {
+            point: Vec2::new(x, y),
+            color,
+            tolerance,
+        }
+    }
+
+    #[test]
+    fn fill_covers_the_transparent_background() {
+        let mut canvas = Canvas::default();
+        canvas.fill(&fill(10.0, 10.0, BLACK, 0));
+        assert!(canvas.pixels.iter().all(|pixel| *pixel == BLACK));
+    }
+
+    #[test]
+    fn fill_stays_inside_closed_shapes() {
+        let red = [255, 0, 0, 255];
+        let mut canvas = Canvas::default();
+        let square = [
+            (100.0, 100.0),
+            (200.0, 100.0),
+            (200.0, 200.0),
+            (100.0, 200.0),
+            (100.0, 100.0),
+        ];
+        canvas.draw(&stroke(&square, 5.0, BLACK));
+        canvas.fill(&fill(150.0, 150.0, red, 0));
+        assert_eq!(canvas.pixel(150, 150), red);
+        assert_eq!(canvas.pixel(103, 103), red);
+        assert_eq!(canvas.pixel(100, 150), BLACK);
+        assert_eq!(canvas.pixel(50, 50), [0; 4]);
+        assert_eq!(canvas.pixel(250, 150), [0; 4]);
+    }
+
+    #[test]
+    fn fill_tolerance_spreads_over_similar_colors() {
+        let dark = [10, 10, 10, 255];
+        let light = [40, 40, 40, 255];
+        let mut canvas = Canvas::default();
+        for (i, pixel) in canvas.pixels.iter_mut().enumerate() {
+            *pixel = if i % IMG_SIZE < IMG_SIZE / 2 {
+                dark
+            } else {
+                light
+            };
+        }
+        let mut strict = canvas.clone();
+        strict.fill(&fill(0.0, 0.0, BLACK, 20));
+        assert_eq!(strict.pixel(IMG_SIZE / 2 - 1, 300), BLACK);
+        assert_eq!(strict.pixel(IMG_SIZE / 2, 300), light);
+
+        canvas.fill(&fill(0.0, 0.0, BLACK, 30));
+        assert_eq!(canvas.pixel(IMG_SIZE - 1, 300), BLACK);
+    }
+
+    #[test]
+    fn fill_out_of_the_canvas_does_nothing() {
+        let mut canvas = Canvas::default();
+        canvas.fill(&fill(-1.0, 10.0, BLACK, 255));
+        canvas.fill(&fill(10.0, IMG_SIZE as f32, BLACK, 255));
+        assert!(canvas == Canvas::default());
+        // Filling with the colour already there is fine too
+        canvas.fill(&fill(10.0, 10.0, [0; 4], 0));
+        assert!(canvas == Canvas::default());
+    }
+
+    #[test]
+    fn strokes_are_clipped_to_the_canvas() {
+        let mut canvas = Canvas::default();
+        canvas.draw(&stroke(&[(-20.0, -20.0), (5.0, 5.0)], 10.0, BLACK));
+        assert_eq!(alpha(&canvas, 0, 0), 255);
+        canvas.draw(&stroke(&[(600.0, 600.0)], 10.0, BLACK));
+        assert_eq!(alpha(&canvas, IMG_SIZE - 1, IMG_SIZE - 1), 0);
+    }
+
+    #[test]
+    fn copied_region_matches_the_full_output() {
+        let mut canvas = Canvas::default();
+        let mut data = canvas.to_bgra();
+        let region = canvas
+            .draw(&stroke(&[(20.0, 30.0), (60.0, 35.0)], 10.0, BLACK))
+            .unwrap();
+        assert_eq!(region.min, (14, 24));
+        assert!(region.max.0 > 60 && region.max.1 > 35);
+        canvas.copy_bgra(region, &mut data);
+        assert_eq!(data, canvas.to_bgra());
+
+        // Strokes entirely out of the canvas change nothing
+        assert_eq!(canvas.draw(&stroke(&[(-50.0, -50.0)], 10.0, BLACK)), None);
+    }
+
+    #[test]
+    fn output_is_bgra() {
+        let data = rasterize(&[Edit::Stroke(stroke(&[(0.5, 0.5)], 1.0, [10, 20, 30, 255]))]);
+        assert_eq!(data.len(), IMG_SIZE * IMG_SIZE * 4);
+        assert_eq!(data[..4], [30, 20, 10, 255]);
+        assert_eq!(data[4..8], [0; 4]);
+    }
+}
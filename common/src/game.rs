@@ -6,18 +6,93 @@ use bevy::prelude::*;
 use bevy_quinnet::shared::ClientId;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    canvas::{rasterize, Edit},
+    strokes::{self, DecodeError, VectorEdit, MAX_POINTS, MAX_STROKES},
+};
+
 /// Single drawing with predetermined size.
 #[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Drawing {
-    pub drawing: Vec<u8>,
+    pub drawing: Picture,
     pub bg_color: [u8; 3],
 }
 
+/// Content of a drawing.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Picture {
+    /// Bgra8 pixels.
+    Pixels(Vec<u8>),
+    /// Strokes and fills in the encoding of [`strokes::encode`], much smaller than the pixels.
+    Strokes(Vec<u8>),
+}
+
+/// Reason a submitted drawing can't be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawingError {
+    /// Pixels don't cover the canvas, holds their byte count.
+    WrongSize(usize),
+    Strokes(DecodeError),
+}
+
+impl From<DecodeError> for DrawingError {
+    fn from(err: DecodeError) -> Self {
+        Self::Strokes(err)
+    }
+}
+
+impl Drawing {
+    /// Drawing sent as strokes and fills, or `None` when they don't fit the vector format.
+    pub fn from_edits(edits: &[Edit], bg_color: [u8; 3]) -> Option<Self> {
+        let vector = edits
+            .iter()
+            .map(VectorEdit::quantize)
+            .collect::<Option<Vec<_>>>()
+            .filter(|vector| {
+                let points = vector.iter().map(VectorEdit::point_count).sum::<usize>();
+                vector.len() <= MAX_STROKES && points <= MAX_POINTS
+            })?;
+        Some(Self {
+            drawing: Picture::Strokes(strokes::encode(&vector)),
+            bg_color,
+        })
+    }
+
+    /// Check receivers can turn the drawing into pixels.
+    pub fn validate(&self) -> Result<(), DrawingError> {
+        match &self.drawing {
+            Picture::Pixels(pixels) => check_size(pixels),
+            Picture::Strokes(data) => Ok(strokes::decode(data).map(|_| ())?),
+        }
+    }
+
+    /// Bgra8 pixels of the drawing.
+    pub fn into_pixels(self) -> Result<Vec<u8>, DrawingError> {
+        match self.drawing {
+            Picture::Pixels(pixels) => check_size(&pixels).map(|_| pixels),
+            Picture::Strokes(data) => {
+                let edits = strokes::decode(&data)?;
+                let edits = edits.iter().map(VectorEdit::to_edit);
+                Ok(rasterize(&edits.collect::<Vec<_>>()))
+            }
+        }
+    }
+}
+
+fn check_size(pixels: &[u8]) -> Result<(), DrawingError> {
+    if pixels.len() != IMG_SIZE * IMG_SIZE * 4 {
+        return Err(DrawingError::WrongSize(pixels.len()));
+    }
+    Ok(())
+}
+
 impl Debug for Drawing {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Drawing")
-            .field("data", &self.drawing.len())
-            .finish()
+        let (kind, len) = match &self.drawing {
+            Picture::Pixels(data) => ("pixels", data.len()),
+            Picture::Strokes(data) => ("strokes", data.len()),
+        };
+        f.debug_struct("Drawing").field(kind, &len).finish()
     }
 }
 
@@ -25,6 +100,9 @@ impl Debug for Drawing {
 #[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct CustomFont(pub usize);
 
+/// Number of fonts a [`CustomFont`] can point at.
+pub const FONT_COUNT: usize = 6;
+
 /// Single user prompt.
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct Prompt {
@@ -54,6 +132,64 @@ pub struct Author {
     pub name: String,
 }
 
+/// Names of everyone behind a combination.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Credits {
+    /// Author of the drawing.
+    pub artist: String,
+    /// Author of the prompt.
+    pub writer: String,
+    /// Author of the combination.
+    pub combiner: String,
+}
+
+/// Players done with the current phase, by name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubmissionProgress {
+    pub done: Vec<String>,
+    pub waiting: Vec<String>,
+}
+
+/// Votes cast so far in the current vote.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VoteTally {
+    pub voted: usize,
+    pub players: usize,
+    /// Votes for each combination, if the room shows them before the result.
+    pub votes: Option<(usize, usize)>,
+}
+
+/// One side of a vote result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteResult {
+    pub drawing: Drawing,
+    pub prompt: Prompt,
+    pub credits: Credits,
+    pub votes: usize,
+    /// Names of the players who voted for it, empty for anonymous votes.
+    pub voters: Vec<String>,
+}
+
+/// Part of a vote result, revealed one after the other on the winner screen.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RevealStage {
+    Votes,
+    Authors,
+    Score,
+}
+
+impl RevealStage {
+    pub const FIRST: Self = Self::Votes;
+
+    pub fn next(self) -> Option<Self> {
+        match self {
+            Self::Votes => Some(Self::Authors),
+            Self::Authors => Some(Self::Score),
+            Self::Score => None,
+        }
+    }
+}
+
 /// Marker for drawing/prompt that's already combined.
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct Combined;
@@ -71,6 +207,7 @@ pub struct Index(pub u64);
 pub struct Indexer(u64);
 
 impl Indexer {
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Index {
         self.0 += 1;
         Index(self.0)
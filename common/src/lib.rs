@@ -1,4 +1,7 @@
 pub mod app;
+pub mod canvas;
 pub mod game;
 pub mod protocol;
+pub mod replay;
+pub mod strokes;
 pub mod transitions;
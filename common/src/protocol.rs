@@ -4,7 +4,10 @@ use bevy::prelude::Event;
 use bevy_quinnet::shared::{channels::ChannelId, ClientId};
 use serde::{Deserialize, Serialize};
 
-use crate::game::{Combination, Drawing, Index, Prompt, Vote};
+use crate::game::{
+    Combination, Drawing, Index, Prompt, RevealStage, SubmissionProgress, Vote, VoteResult,
+    VoteTally,
+};
 
 #[derive(Event)]
 pub struct NetMsg<T> {
@@ -25,11 +28,37 @@ impl<T> NetMsg<T> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientMsgRoot {
-    Connect { name: String },
+    Connect {
+        name: String,
+    },
     Comm(ClientMsgComm),
+    /// Clock sync request, answered with [`ServerMsgRoot::Pong`].
+    Ping {
+        sent: Duration,
+    },
+    /// Only accepted from the host of the room.
+    Host(HostCommand),
     Disconnect,
 }
 
+/// Control over the match, reserved to the host.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HostCommand {
+    /// Stop the countdown of the current phase.
+    Pause,
+    Resume,
+    /// Push the deadline of the current phase back.
+    Extend(Duration),
+    /// End the current phase now, late submissions still get the grace period.
+    Skip,
+    /// Let a prompt held back by the word filter into the match.
+    ApprovePrompt(Index),
+    /// Drop a prompt held back by the word filter, its author can write another one.
+    RejectPrompt(Index),
+    /// Theme of the next drawing rounds in place of the schedule's, `None` goes back to it.
+    SetTheme(Option<String>),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientMsgComm {
     SubmitDrawing(Drawing),
@@ -48,25 +77,110 @@ impl ClientMsgComm {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ServerMsgRoot {
     Draw {
-        duration: Duration,
+        deadline: Deadline,
+        /// Number of drawings to submit.
+        drawings: usize,
+        /// Theme or constraint for the drawing, freeform if not set.
+        theme: Option<String>,
     },
     Prompt {
-        duration: Duration,
+        deadline: Deadline,
+        /// Number of prompts to submit.
+        prompts: usize,
     },
     Combine {
-        duration: Duration,
+        deadline: Deadline,
         drawings: Vec<(Index, Drawing)>,
         prompts: Vec<(Index, Prompt)>,
     },
     Vote {
-        duration: Duration,
+        deadline: Deadline,
         combination1: (Index, Drawing, Prompt),
         combination2: (Index, Drawing, Prompt),
     },
+    /// Players done with the current phase, sent as it changes.
+    Progress(SubmissionProgress),
+    /// Votes cast so far, sent as they change if the room has live tallies.
+    Tally(VoteTally),
+    /// Result of a vote, parts of it are shown as [`ServerMsgRoot::Reveal`] messages arrive.
     Winner {
-        duration: Duration,
-        drawing: Drawing,
-        prompt: Prompt,
+        deadline: Deadline,
+        // Boxed to keep the other messages small
+        winner: Box<VoteResult>,
+        loser: Box<VoteResult>,
+        /// Points each author of the winner earned.
+        points: u32,
     },
+    /// Show a part of the vote result, along with every part before it.
+    Reveal(RevealStage),
     Wait,
+    /// The current phase is out of time, work in progress should be submitted now.
+    PhaseEnding,
+    /// Deadline of the current phase changed, or the phase was paused or resumed.
+    Timer {
+        deadline: Deadline,
+        /// Server time the phase was paused at.
+        paused_at: Option<Duration>,
+    },
+    /// The client is now the host of the room.
+    Host,
+    /// Prompt held back by the word filter, for the host to approve or reject.
+    PromptReview {
+        prompt: (Index, Prompt),
+        author: String,
+    },
+    /// Prompt of the client refused by the word filter or the host, it doesn't count toward the
+    /// prompts to submit.
+    PromptRejected(Prompt),
+    /// Answer to [`ClientMsgRoot::Ping`].
+    Pong {
+        /// Client time the ping was sent at.
+        sent: Duration,
+        /// Server time the ping was answered at.
+        server_time: Duration,
+    },
+}
+
+impl ServerMsgRoot {
+    /// Deadline of the phase the message starts.
+    pub fn deadline_mut(&mut self) -> Option<&mut Deadline> {
+        match self {
+            ServerMsgRoot::Draw { deadline, .. }
+            | ServerMsgRoot::Prompt { deadline, .. }
+            | ServerMsgRoot::Combine { deadline, .. }
+            | ServerMsgRoot::Vote { deadline, .. }
+            | ServerMsgRoot::Winner { deadline, .. }
+            | ServerMsgRoot::Timer { deadline, .. } => Some(deadline),
+            ServerMsgRoot::Wait
+            | ServerMsgRoot::PhaseEnding
+            | ServerMsgRoot::Reveal(_)
+            | ServerMsgRoot::Tally(_)
+            | ServerMsgRoot::Progress(_)
+            | ServerMsgRoot::Host
+            | ServerMsgRoot::PromptReview { .. }
+            | ServerMsgRoot::PromptRejected(_)
+            | ServerMsgRoot::Pong { .. } => None,
+        }
+    }
+}
+
+/// End of a phase, in server time.
+///
+/// Server time is the time since the server started, clients estimate it with
+/// [`ClientMsgRoot::Ping`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deadline {
+    /// Server time at which the phase ends.
+    pub at: Duration,
+    /// Length of the whole phase.
+    pub duration: Duration,
+}
+
+impl Deadline {
+    pub fn new(started: Duration, duration: Duration) -> Self {
+        Self {
+            at: started + duration,
+            duration,
+        }
+    }
 }
@@ -0,0 +1,84 @@
+//! Recorded matches.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+    time::Duration,
+};
+
+use bevy_quinnet::shared::ClientId;
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{ClientMsgRoot, ServerMsgRoot};
+
+/// Every message of a match, with the state needed to play it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// Seed of the match randomness.
+    pub seed: u64,
+    /// Server time the match started at.
+    pub started: Duration,
+    /// Server game configuration, as JSON.
+    pub config: String,
+    /// Word filter of the server, as JSON.
+    pub word_filter: String,
+    /// House prompts of the server, as JSON.
+    pub prompt_bank: String,
+    /// Drawing themes of the server, as JSON.
+    pub themes: String,
+    /// Players of the match and their names.
+    pub players: Vec<(ClientId, String)>,
+    pub entries: Vec<ReplayEntry>,
+}
+
+/// Message or event, timed from the start of the match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    pub time: Duration,
+    pub event: ReplayEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReplayEvent {
+    /// Message received by the server.
+    Inbound(ClientId, ClientMsgRoot),
+    /// Message sent by the server.
+    Outbound(ClientId, ServerMsgRoot),
+    /// Client connection lost.
+    Lost(ClientId),
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self).map_err(io::Error::other)
+    }
+
+    /// Client id of a player by name.
+    pub fn player(&self, name: &str) -> Option<ClientId> {
+        self.players
+            .iter()
+            .find(|(_, player)| player == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Messages a client received.
+    pub fn received_by(
+        &self,
+        client: ClientId,
+    ) -> impl Iterator<Item = (Duration, &ServerMsgRoot)> {
+        self.entries
+            .iter()
+            .filter_map(move |entry| match &entry.event {
+                ReplayEvent::Outbound(id, data) if *id == client => Some((entry.time, data)),
+                _ => None,
+            })
+    }
+}
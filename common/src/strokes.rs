@@ -0,0 +1,568 @@
+//! Compact wire format of drawings made of strokes, rasterised by receivers.
+//!
+//! Each stroke starts with a byte holding its brush size index in the low 3 bits, its colour
+//! index in the next 4 and the eraser flag in the high bit. It's followed by the number of points,
+//! the first point, then the offset of every other point from the previous one, all as
+//! variable-length integers.
+//!
+//! A fill uses the otherwise unused brush size index 7 in the same header byte, with the eraser
+//! flag standing for a transparent fill. It's followed by the seed point as variable-length
+//! integers and the tolerance byte. Strokes and fills follow each other until the end of the data.
+
+use bevy::math::Vec2;
+
+use crate::canvas::{Edit, Fill, Stroke};
+
+/// Brush diameters a stroke can use.
+pub const BRUSH_SIZES: [f32; 5] = [3.0, 5.0, 13.0, 21.0, 43.0];
+/// Brush colours a stroke can use, in RGB.
+pub const BRUSH_COLORS: [[u8; 3]; 10] = [
+    [0, 0, 0],
+    [160, 160, 160],
+    [255, 255, 255],
+    [255, 0, 0],
+    [139, 0, 0],
+    [0, 255, 0],
+    [0, 100, 0],
+    [0, 0, 255],
+    [0, 0, 139],
+    [255, 215, 0],
+];
+/// Most strokes and fills a drawing can be made of.
+pub const MAX_STROKES: usize = 1000;
+/// Most points all strokes of a drawing can have together, which bounds the cost of rasterising it.
+pub const MAX_POINTS: usize = 20_000;
+
+const SIZE_BITS: u8 = 0b0000_0111;
+const COLOR_SHIFT: u8 = 3;
+const COLOR_BITS: u8 = 0b0111_1000;
+const ERASER_BIT: u8 = 0b1000_0000;
+/// Brush size index marking a fill.
+const FILL_SIZE: u8 = SIZE_BITS;
+
+/// Stroke with its points snapped to pixels and its brush picked from the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorStroke {
+    /// Pixels the stroke goes through, which can be out of the canvas.
+    pub points: Vec<[i16; 2]>,
+    /// Index in [`BRUSH_SIZES`].
+    pub size: u8,
+    /// Index in [`BRUSH_COLORS`], unused by the eraser.
+    pub color: u8,
+    pub eraser: bool,
+}
+
+impl VectorStroke {
+    /// Stroke snapped to pixels, if its brush is in the palette.
+    pub fn quantize(stroke: &Stroke) -> Option<Self> {
+        let size = BRUSH_SIZES.iter().position(|size| *size == stroke.width)?;
+        let color = if stroke.eraser {
+            0
+        } else {
+            palette_index(stroke.color)?
+        };
+        let mut points = stroke
+            .points
+            .iter()
+            .map(|point| [point.x.floor() as i16, point.y.floor() as i16])
+            .collect::<Vec<_>>();
+        points.dedup();
+        if points.is_empty() {
+            return None;
+        }
+        Some(Self {
+            points,
+            size: size as u8,
+            color,
+            eraser: stroke.eraser,
+        })
+    }
+
+    /// Stroke going through the center of its pixels.
+    pub fn to_stroke(&self) -> Stroke {
+        Stroke {
+            points: self
+                .points
+                .iter()
+                .map(|[x, y]| Vec2::new(*x as f32 + 0.5, *y as f32 + 0.5))
+                .collect(),
+            width: BRUSH_SIZES[self.size as usize],
+            color: palette_color(self.color, self.eraser),
+            eraser: self.eraser,
+        }
+    }
+}
+
+/// Paint bucket fill with its point snapped to a pixel and its colour picked from the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorFill {
+    /// Pixel the fill starts from, which can be out of the canvas.
+    pub point: [i16; 2],
+    /// Index in [`BRUSH_COLORS`], unused when filling with transparency.
+    pub color: u8,
+    /// Fill with transparency, like the eraser.
+    pub eraser: bool,
+    pub tolerance: u8,
+}
+
+impl VectorFill {
+    /// Fill snapped to a pixel, if its colour is in the palette or transparent.
+    pub fn quantize(fill: &Fill) -> Option<Self> {
+        let eraser = fill.color == [0; 4];
+        let color = if eraser {
+            0
+        } else {
+            palette_index(fill.color)?
+        };
+        Some(Self {
+            point: [fill.point.x.floor() as i16, fill.point.y.floor() as i16],
+            color,
+            eraser,
+            tolerance: fill.tolerance,
+        })
+    }
+
+    /// Fill starting from the center of its pixel.
+    pub fn to_fill(&self) -> Fill {
+        let [x, y] = self.point;
+        Fill {
+            point: Vec2::new(x as f32 + 0.5, y as f32 + 0.5),
+            color: palette_color(self.color, self.eraser),
+            tolerance: self.tolerance,
+        }
+    }
+}
+
+/// Edit in the vector format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VectorEdit {
+    Stroke(VectorStroke),
+    Fill(VectorFill),
+}
+
+impl VectorEdit {
+    pub fn quantize(edit: &Edit) -> Option<Self> {
+        match edit {
+            Edit::Stroke(stroke) => VectorStroke::quantize(stroke).map(Self::Stroke),
+            Edit::Fill(fill) => VectorFill::quantize(fill).map(Self::Fill),
+        }
+    }
+
+    /// Points the edit adds towards [`MAX_POINTS`].
+    pub fn point_count(&self) -> usize {
+        match self {
+            Self::Stroke(stroke) => stroke.points.len(),
+            Self::Fill(_) => 0,
+        }
+    }
+
+    pub fn to_edit(&self) -> Edit {
+        match self {
+            Self::Stroke(stroke) => Edit::Stroke(stroke.to_stroke()),
+            Self::Fill(fill) => Edit::Fill(fill.to_fill()),
+        }
+    }
+}
+
+/// Index of an opaque colour in [`BRUSH_COLORS`].
+fn palette_index([r, g, b, a]: [u8; 4]) -> Option<u8> {
+    if a != u8::MAX {
+        return None;
+    }
+    let index = BRUSH_COLORS.iter().position(|color| *color == [r, g, b])?;
+    Some(index as u8)
+}
+
+/// Premultiplied RGBA of a palette colour, transparent for the eraser.
+fn palette_color(index: u8, eraser: bool) -> [u8; 4] {
+    if eraser {
+        return [0; 4];
+    }
+    let [r, g, b] = BRUSH_COLORS[index as usize];
+    [r, g, b, u8::MAX]
+}
+
+/// Reason encoded strokes can't be read back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Data ends in the middle of a stroke or fill.
+    Truncated,
+    /// Brush size or colour isn't in the palette.
+    UnknownBrush,
+    /// Point doesn't fit the coordinates of a [`VectorStroke`] or [`VectorFill`].
+    OutOfRange,
+    EmptyStroke,
+    /// More than [`MAX_STROKES`] strokes and fills.
+    TooManyStrokes,
+    /// More than [`MAX_POINTS`] points.
+    TooManyPoints,
+}
+
+pub fn encode(edits: &[VectorEdit]) -> Vec<u8> {
+    let mut data = vec![];
+    for edit in edits {
+        match edit {
+            VectorEdit::Stroke(stroke) => {
+                data.push(header(stroke.size, stroke.color, stroke.eraser));
+                write_varint(&mut data, stroke.points.len() as u32);
+                let mut previous = [0, 0];
+                for point in &stroke.points {
+                    for (value, previous) in point.iter().zip(previous) {
+                        write_varint(&mut data, zigzag(*value as i32 - previous as i32));
+                    }
+                    previous = *point;
+                }
+            }
+            VectorEdit::Fill(fill) => {
+                data.push(header(FILL_SIZE, fill.color, fill.eraser));
+                for value in fill.point {
+                    write_varint(&mut data, zigzag(value as i32));
+                }
+                data.push(fill.tolerance);
+            }
+        }
+    }
+    data
+}
+
+pub fn decode(data: &[u8]) -> Result<Vec<VectorEdit>, DecodeError> {
+    let mut reader = Reader { data };
+    let mut edits = vec![];
+    let mut total_points = 0;
+    while let Some(header) = reader.next_byte() {
+        if edits.len() >= MAX_STROKES {
+            return Err(DecodeError::TooManyStrokes);
+        }
+        let size = header & SIZE_BITS;
+        let color = (header & COLOR_BITS) >> COLOR_SHIFT;
+        let eraser = header & ERASER_BIT != 0;
+        if color as usize >= BRUSH_COLORS.len() {
+            return Err(DecodeError::UnknownBrush);
+        }
+        if size == FILL_SIZE {
+            let mut point = [0; 2];
+            for value in &mut point {
+                *value = i16::try_from(unzigzag(reader.varint()?))
+                    .map_err(|_| DecodeError::OutOfRange)?;
+            }
+            edits.push(VectorEdit::Fill(VectorFill {
+                point,
+                color,
+                eraser,
+                tolerance: reader.next_byte().ok_or(DecodeError::Truncated)?,
+            }));
+            continue;
+        }
+        if size as usize >= BRUSH_SIZES.len() {
+            return Err(DecodeError::UnknownBrush);
+        }
+        let count = reader.varint()? as usize;
+        if count == 0 {
+            return Err(DecodeError::EmptyStroke);
+        }
+        // Every point takes at least two bytes, so garbage can't make a huge allocation
+        if count > reader.data.len() / 2 {
+            return Err(DecodeError::Truncated);
+        }
+        total_points += count;
+        if total_points > MAX_POINTS {
+            return Err(DecodeError::TooManyPoints);
+        }
+        let mut points = Vec::with_capacity(count);
+        let mut previous = [0, 0];
+        for _ in 0..count {
+            let mut point = [0; 2];
+            for (value, previous) in point.iter_mut().zip(previous) {
+                let offset = unzigzag(reader.varint()?);
+                *value =
+                    i16::try_from(previous as i32 + offset).map_err(|_| DecodeError::OutOfRange)?;
+            }
+            points.push(point);
+            previous = point;
+        }
+        edits.push(VectorEdit::Stroke(VectorStroke {
+            points,
+            size,
+            color,
+            eraser,
+        }));
+    }
+    Ok(edits)
+}
+
+fn header(size: u8, color: u8, eraser: bool) -> u8 {
+    let mut header = size & SIZE_BITS | (color << COLOR_SHIFT) & COLOR_BITS;
+    if eraser {
+        header |= ERASER_BIT;
+    }
+    header
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+/// Map signed values to unsigned ones, keeping small negative values small.
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn unzigzag(value: u32) -> i32 {
+    (value >> 1) as i32 ^ -((value & 1) as i32)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn next_byte(&mut self) -> Option<u8> {
+        let (byte, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(*byte)
+    }
+
+    fn varint(&mut self) -> Result<u32, DecodeError> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = self.next_byte().ok_or(DecodeError::Truncated)?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::OutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strokes() -> Vec<VectorEdit> {
+        vec![
+            VectorEdit::Stroke(VectorStroke {
+                points: vec![[10, 20], [11, 22], [300, 5], [-40, 600]],
+                size: 2,
+                color: 9,
+                eraser: false,
+            }),
+            VectorEdit::Fill(VectorFill {
+                point: [-3, 400],
+                color: 7,
+                eraser: false,
+                tolerance: 64,
+            }),
+            VectorEdit::Stroke(VectorStroke {
+                points: vec![[511, 511]],
+                size: 4,
+                color: 0,
+                eraser: true,
+            }),
+        ]
+    }
+
+    #[test]
+    fn strokes_round_trip() {
+        let strokes = strokes();
+        assert_eq!(decode(&encode(&strokes)), Ok(strokes));
+        assert_eq!(decode(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn extreme_coordinates_round_trip() {
+        let strokes = vec![
+            VectorEdit::Stroke(VectorStroke {
+                points: vec![[i16::MIN, i16::MAX], [i16::MAX, i16::MIN]],
+                size: 0,
+                color: 0,
+                eraser: false,
+            }),
+            VectorEdit::Fill(VectorFill {
+                point: [i16::MAX, i16::MIN],
+                color: 0,
+                eraser: true,
+                tolerance: u8::MAX,
+            }),
+        ];
+        assert_eq!(decode(&encode(&strokes)), Ok(strokes));
+    }
+
+    #[test]
+    fn small_moves_take_a_byte_per_coordinate() {
+        let stroke = VectorStroke {
+            points: (0..100).map(|i| [100 + i, 200 - i]).collect(),
+            size: 0,
+            color: 0,
+            eraser: false,
+        };
+        // Header, count, first point and one byte per offset
+        assert_eq!(
+            encode(&[VectorEdit::Stroke(stroke)]).len(),
+            1 + 1 + 4 + 99 * 2
+        );
+    }
+
+    #[test]
+    fn quantized_fill_keeps_its_colour() {
+        let fill = Fill {
+            point: Vec2::new(10.7, 20.2),
+            color: [0, 0, 255, 255],
+            tolerance: 64,
+        };
+        let quantized = VectorFill::quantize(&fill).unwrap();
+        assert_eq!((quantized.point, quantized.color), ([10, 20], 7));
+        let restored = quantized.to_fill();
+        assert_eq!(restored.point, Vec2::new(10.5, 20.5));
+        assert_eq!(
+            (restored.color, restored.tolerance),
+            (fill.color, fill.tolerance)
+        );
+
+        let clear = VectorFill::quantize(&Fill {
+            color: [0; 4],
+            ..fill
+        })
+        .unwrap();
+        assert!(clear.eraser);
+        assert_eq!(clear.to_fill().color, [0; 4]);
+        assert_eq!(
+            VectorFill::quantize(&Fill {
+                color: [1, 2, 3, 255],
+                ..fill
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn quantized_stroke_keeps_its_brush() {
+        let stroke = Stroke {
+            points: vec![
+                Vec2::new(10.2, 20.9),
+                Vec2::new(10.7, 20.1),
+                Vec2::new(30.5, -4.5),
+            ],
+            width: BRUSH_SIZES[3],
+            color: [255, 215, 0, 255],
+            eraser: false,
+        };
+        let quantized = VectorStroke::quantize(&stroke).unwrap();
+        assert_eq!(quantized.points, vec![[10, 20], [30, -5]]);
+        assert_eq!((quantized.size, quantized.color), (3, 9));
+        let restored = quantized.to_stroke();
+        assert_eq!(restored.points[0], Vec2::new(10.5, 20.5));
+        assert_eq!(
+            (restored.width, restored.color),
+            (stroke.width, stroke.color)
+        );
+    }
+
+    #[test]
+    fn brushes_out_of_the_palette_are_not_quantized() {
+        let stroke = Stroke {
+            points: vec![Vec2::ZERO],
+            width: 4.0,
+            color: [0, 0, 0, 255],
+            eraser: false,
+        };
+        assert_eq!(VectorStroke::quantize(&stroke), None);
+        let stroke = Stroke {
+            width: BRUSH_SIZES[0],
+            color: [1, 2, 3, 255],
+            ..stroke
+        };
+        assert_eq!(VectorStroke::quantize(&stroke), None);
+    }
+
+    #[test]
+    fn decoded_edits_paint_like_the_originals() {
+        let edits = [
+            Edit::Stroke(Stroke {
+                points: vec![Vec2::new(0.5, 100.5), Vec2::new(511.5, 100.5)],
+                width: BRUSH_SIZES[1],
+                color: [0, 0, 0, 255],
+                eraser: false,
+            }),
+            Edit::Fill(Fill {
+                point: Vec2::new(200.5, 300.5),
+                color: [255, 0, 0, 255],
+                tolerance: 64,
+            }),
+        ];
+        let vector = edits
+            .iter()
+            .map(|edit| VectorEdit::quantize(edit).unwrap())
+            .collect::<Vec<_>>();
+        let decoded = decode(&encode(&vector)).unwrap();
+        let decoded = decoded.iter().map(VectorEdit::to_edit).collect::<Vec<_>>();
+        assert_eq!(decoded, edits);
+    }
+
+    #[test]
+    fn invalid_data_is_rejected() {
+        let data = encode(&strokes());
+        assert_eq!(decode(&data[..data.len() - 1]), Err(DecodeError::Truncated));
+        // Colour index 15
+        assert_eq!(
+            decode(&[0b0111_1000, 1, 0, 0]),
+            Err(DecodeError::UnknownBrush)
+        );
+        assert_eq!(decode(&[0, 0]), Err(DecodeError::EmptyStroke));
+        // Size index 5 is neither a brush nor a fill
+        assert_eq!(decode(&[5, 1, 0, 0]), Err(DecodeError::UnknownBrush));
+        // Fill without its tolerance
+        assert_eq!(decode(&[FILL_SIZE, 0, 0]), Err(DecodeError::Truncated));
+        // Huge point count
+        assert_eq!(
+            decode(&[0, 0xff, 0xff, 0xff, 0x7f, 0, 0]),
+            Err(DecodeError::Truncated)
+        );
+        // Offset pushing a coordinate out of range
+        let mut data = vec![];
+        write_varint(&mut data, zigzag(i16::MAX as i32 + 1));
+        assert_eq!(
+            decode(&[&[0, 1][..], &data, &[0]].concat()),
+            Err(DecodeError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn stroke_count_is_limited() {
+        let dot = VectorEdit::Stroke(VectorStroke {
+            points: vec![[0, 0]],
+            size: 0,
+            color: 0,
+            eraser: false,
+        });
+        let strokes = vec![dot; MAX_STROKES];
+        assert!(decode(&encode(&strokes)).is_ok());
+        let mut strokes = strokes;
+        strokes.push(strokes[0].clone());
+        assert_eq!(decode(&encode(&strokes)), Err(DecodeError::TooManyStrokes));
+    }
+
+    #[test]
+    fn point_count_is_limited() {
+        let line = |points: usize| {
+            VectorEdit::Stroke(VectorStroke {
+                points: (0..points as i16).map(|i| [i, 0]).collect(),
+                size: 0,
+                color: 0,
+                eraser: false,
+            })
+        };
+        let strokes = vec![line(MAX_POINTS / 2), line(MAX_POINTS / 2)];
+        assert!(decode(&encode(&strokes)).is_ok());
+        let strokes = vec![line(MAX_POINTS / 2), line(MAX_POINTS / 2 + 1)];
+        assert_eq!(decode(&encode(&strokes)), Err(DecodeError::TooManyPoints));
+        assert_eq!(
+            decode(&encode(&[line(MAX_POINTS + 1)])),
+            Err(DecodeError::TooManyPoints)
+        );
+    }
+}
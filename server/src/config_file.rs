@@ -0,0 +1,37 @@
+//! Optional plain text configuration files.
+
+use std::{fmt::Display, fs, io, path::Path, str::FromStr};
+
+use bevy::prelude::*;
+
+/// Read and parse a file.
+pub fn load<T>(path: impl AsRef<Path>) -> io::Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let content = fs::read_to_string(path)?;
+    content
+        .parse()
+        .map_err(|err: T::Err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+}
+
+/// Read and parse a file, falling back to the default if it's missing or invalid.
+pub fn load_or_default<T>(path: impl AsRef<Path>) -> T
+where
+    T: FromStr + Default,
+    T::Err: Display,
+{
+    let path = path.as_ref();
+    match load(path) {
+        Ok(value) => {
+            info!(path = %path.display(), "Config file loaded.");
+            value
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => T::default(),
+        Err(err) => {
+            warn!(path = %path.display(), %err, "Failed to load config file.");
+            T::default()
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Word filter applied to user submitted text.
+
+use std::{ops::Range, str::FromStr};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Default location of the word list, relative to the working directory.
+pub const WORD_FILTER_PATH: &str = "word_filter.txt";
+
+/// How filtered words are matched against text.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Only whole words match.
+    #[default]
+    Exact,
+    /// Words match anywhere, including inside other words.
+    Substring,
+    /// Like [`MatchMode::Substring`], but digits and symbols are read as letters first.
+    Leetspeak,
+}
+
+/// What happens to text that contains a filtered word.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Send the submission back to its author.
+    #[default]
+    Reject,
+    /// Replace every character of a filtered word with an asterisk.
+    Mask,
+    /// Keep the submission out of play until the host approves it.
+    Review,
+}
+
+/// Outcome of running text through the filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    Masked(String),
+    Rejected,
+    Review,
+}
+
+/// Word list with matching rules.
+///
+/// The file format is one word per line, `#` starts a comment.
+/// Directive lines `@match <exact|substring|leetspeak>`
+/// and `@action <reject|mask|review>` configure the filter.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WordFilter {
+    pub words: Vec<String>,
+    pub matching: MatchMode,
+    pub action: FilterAction,
+}
+
+impl WordFilter {
+    /// Check text against the word list.
+    pub fn check(&self, text: &str) -> Verdict {
+        let matches = self.find(text);
+        if matches.is_empty() {
+            return Verdict::Clean;
+        }
+        match self.action {
+            FilterAction::Reject => Verdict::Rejected,
+            FilterAction::Review => Verdict::Review,
+            FilterAction::Mask => {
+                let mut chars = text.chars().collect::<Vec<_>>();
+                for range in matches {
+                    chars[range].fill('*');
+                }
+                Verdict::Masked(chars.into_iter().collect())
+            }
+        }
+    }
+
+    /// Character ranges of all filtered words in the text.
+    fn find(&self, text: &str) -> Vec<Range<usize>> {
+        let haystack = normalize(text, self.matching == MatchMode::Leetspeak);
+        let mut matches = vec![];
+        for word in &self.words {
+            let needle = normalize(word, self.matching == MatchMode::Leetspeak);
+            if needle.is_empty() || needle.len() > haystack.len() {
+                continue;
+            }
+            for start in 0..=haystack.len() - needle.len() {
+                let end = start + needle.len();
+                if haystack[start..end] != needle[..] {
+                    continue;
+                }
+                let whole_word = (start == 0 || !haystack[start - 1].is_alphanumeric())
+                    && (end == haystack.len() || !haystack[end].is_alphanumeric());
+                if self.matching != MatchMode::Exact || whole_word {
+                    matches.push(start..end);
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// Lowercase text one character at a time, so indices keep pointing at the original characters.
+fn normalize(text: &str, leetspeak: bool) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .map(|c| if leetspeak { unleet(c) } else { c })
+        .collect()
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' | '|' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' | '+' => 't',
+        '8' => 'b',
+        '9' => 'g',
+        c => c,
+    }
+}
+
+impl FromStr for WordFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = WordFilter::default();
+        for line in s.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some(directive) = line.strip_prefix('@') else {
+                filter.words.push(line.to_owned());
+                continue;
+            };
+            let (key, value) = directive
+                .split_once(char::is_whitespace)
+                .map(|(k, v)| (k, v.trim()))
+                .ok_or_else(|| format!("Directive without a value: {line}"))?;
+            match (key, value) {
+                ("match", "exact") => filter.matching = MatchMode::Exact,
+                ("match", "substring") => filter.matching = MatchMode::Substring,
+                ("match", "leetspeak") => filter.matching = MatchMode::Leetspeak,
+                ("action", "reject") => filter.action = FilterAction::Reject,
+                ("action", "mask") => filter.action = FilterAction::Mask,
+                ("action", "review") => filter.action = FilterAction::Review,
+                _ => return Err(format!("Unknown directive: {line}")),
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Marker for submissions held back by the word filter.
+#[derive(Component, Debug, Clone)]
+pub struct Flagged;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(matching: MatchMode, action: FilterAction) -> WordFilter {
+        WordFilter {
+            words: vec!["ape".to_owned(), "box".to_owned()],
+            matching,
+            action,
+        }
+    }
+
+    #[test]
+    fn exact_matches_whole_words_only() {
+        let filter = filter(MatchMode::Exact, FilterAction::Reject);
+        assert_eq!(filter.check("an APE, in a box!"), Verdict::Rejected);
+        assert_eq!(filter.check("grapes in a boxcar"), Verdict::Clean);
+    }
+
+    #[test]
+    fn substring_matches_inside_words() {
+        let filter = filter(MatchMode::Substring, FilterAction::Mask);
+        assert_eq!(
+            filter.check("Grapes in a boxcar"),
+            Verdict::Masked("Gr***s in a ***car".to_owned())
+        );
+    }
+
+    #[test]
+    fn leetspeak_is_normalized() {
+        let filter = filter(MatchMode::Leetspeak, FilterAction::Mask);
+        assert_eq!(
+            filter.check("b0x of 4p3s"),
+            Verdict::Masked("*** of ***s".to_owned())
+        );
+        let filter = self::filter(MatchMode::Substring, FilterAction::Mask);
+        assert_eq!(filter.check("b0x of 4p3s"), Verdict::Clean);
+    }
+
+    #[test]
+    fn review_holds_back() {
+        let filter = filter(MatchMode::Exact, FilterAction::Review);
+        assert_eq!(filter.check("ape"), Verdict::Review);
+        assert_eq!(filter.check("monkey"), Verdict::Clean);
+    }
+
+    #[test]
+    fn parses_word_list() {
+        let filter = "# comment\n@match leetspeak\n@action mask\n\nape  # trailing\n  box\n"
+            .parse::<WordFilter>()
+            .unwrap();
+        assert_eq!(
+            filter,
+            self::filter(MatchMode::Leetspeak, FilterAction::Mask)
+        );
+        assert!("@match sometimes".parse::<WordFilter>().is_err());
+        assert!("@action".parse::<WordFilter>().is_err());
+    }
+}
@@ -1,8 +1,11 @@
 use std::time::Duration;
 
 use bevy::prelude::Resource;
+use common::game::RevealStage;
 use serde::{Deserialize, Serialize};
 
+use crate::themes::ThemeChoice;
+
 #[derive(Resource, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum StateData {
     Draw(DrawConfig),
@@ -13,13 +16,19 @@ pub enum StateData {
 
 #[derive(Resource, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DrawConfig {
+    pub drawings_per_player: usize,
     pub duration: Duration,
+    pub theme: ThemeChoice,
 }
 
 #[derive(Resource, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PromptConfig {
     pub prompts_per_player: usize,
     pub duration: Duration,
+    /// Share of the prompt quota mixed in from the prompt bank.
+    pub house_ratio: f32,
+    /// Prompt bank category to pick house prompts from, any if not set.
+    pub house_category: Option<String>,
 }
 
 #[derive(Resource, Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -31,11 +40,61 @@ pub struct CombineConfig {
 pub struct VoteConfig {
     pub voting_duration: Duration,
     pub winner_duration: Duration,
+    #[serde(default)]
+    pub reveal: RevealConfig,
+    /// Only send vote counts in results, without who voted for what.
+    #[serde(default)]
+    pub anonymous: bool,
+    #[serde(default)]
+    pub live_tally: LiveTally,
+}
+
+/// What players see of the votes while voting is still going.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LiveTally {
+    /// Nothing until the result.
+    #[default]
+    Off,
+    /// How many players voted.
+    Voted,
+    /// How many players voted and the votes for each combination.
+    Counts,
+}
+
+/// When each part of a vote result is revealed, counted from the start of the winner screen.
+///
+/// Parts are always revealed in order, one set early waits for the part before it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RevealConfig {
+    pub votes: Duration,
+    pub authors: Duration,
+    pub score: Duration,
+}
+
+impl RevealConfig {
+    pub fn at(&self, stage: RevealStage) -> Duration {
+        match stage {
+            RevealStage::Votes => self.votes,
+            RevealStage::Authors => self.authors,
+            RevealStage::Score => self.score,
+        }
+    }
+}
+
+impl Default for RevealConfig {
+    fn default() -> Self {
+        Self {
+            votes: Duration::from_millis(1500),
+            authors: Duration::from_millis(3500),
+            score: Duration::from_millis(5500),
+        }
+    }
 }
 
 /// Game configuration.
-#[derive(Resource)]
+#[derive(Resource, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct GameConfig {
+    /// Grace period after each deadline, for work clients submit when their timer runs out.
     pub extra_time: Duration,
     pub states: Vec<StateData>,
 }
@@ -47,7 +106,10 @@ impl GameConfig {
             states: vec![
                 StateData::Vote(VoteConfig {
                     voting_duration: Duration::from_secs(10),
-                    winner_duration: Duration::from_secs(5),
+                    winner_duration: Duration::from_secs(8),
+                    reveal: RevealConfig::default(),
+                    anonymous: false,
+                    live_tally: LiveTally::Voted,
                 }),
                 StateData::Combine(CombineConfig {
                     duration: Duration::from_secs(30),
@@ -55,13 +117,20 @@ impl GameConfig {
                 StateData::Prompt(PromptConfig {
                     prompts_per_player: 4,
                     duration: Duration::from_secs(60),
+                    house_ratio: 0.25,
+                    house_category: None,
                 }),
                 StateData::Draw(DrawConfig {
+                    drawings_per_player: 1,
                     duration: Duration::from_secs(180),
+                    theme: ThemeChoice::Random,
                 }),
                 StateData::Vote(VoteConfig {
                     voting_duration: Duration::from_secs(10),
-                    winner_duration: Duration::from_secs(5),
+                    winner_duration: Duration::from_secs(8),
+                    reveal: RevealConfig::default(),
+                    anonymous: false,
+                    live_tally: LiveTally::Voted,
                 }),
                 StateData::Combine(CombineConfig {
                     duration: Duration::from_secs(30),
@@ -69,12 +138,13 @@ impl GameConfig {
                 StateData::Prompt(PromptConfig {
                     prompts_per_player: 3,
                     duration: Duration::from_secs(60),
+                    house_ratio: 0.0,
+                    house_category: None,
                 }),
                 StateData::Draw(DrawConfig {
-                    duration: Duration::from_secs(180),
-                }),
-                StateData::Draw(DrawConfig {
-                    duration: Duration::from_secs(180),
+                    drawings_per_player: 2,
+                    duration: Duration::from_secs(360),
+                    theme: ThemeChoice::None,
                 }),
             ],
         }
@@ -84,3 +154,13 @@ impl GameConfig {
         self.states.pop()
     }
 }
+
+/// Game configuration every room starts with.
+#[derive(Resource, Debug, Clone)]
+pub struct DefaultGameConfig(pub GameConfig);
+
+impl Default for DefaultGameConfig {
+    fn default() -> Self {
+        Self(GameConfig::long())
+    }
+}
@@ -0,0 +1,202 @@
+pub mod config_file;
+pub mod filter;
+pub mod game;
+pub mod modes;
+pub mod networking;
+pub mod progress;
+pub mod prompt_bank;
+pub mod replay;
+pub mod rng;
+pub mod scores;
+pub mod states;
+pub mod themes;
+pub mod timer;
+pub mod transport;
+pub mod users;
+
+use bevy::dev_tools::states::log_transitions;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use common::{
+    app::AppExt,
+    protocol::{ClientMsgComm, NetMsg},
+    transitions::IdentityTransitionsPlugin,
+};
+use filter::WordFilter;
+use game::{DefaultGameConfig, GameConfig, StateData};
+use modes::ModesPlugin;
+use progress::PhaseProgress;
+use prompt_bank::PromptBank;
+use replay::ReplayRecorderPlugin;
+use rng::MatchSeed;
+use states::{GameState, RoomState, ServerState, VoteState};
+use themes::{HostTheme, ThemeList};
+use timer::{HostRequest, PhaseTimer};
+use transport::{TransportPlugin, TransportSet};
+use users::Users;
+
+/// Game logic of the server, to be paired with a transport plugin.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+        app.add_plugins((
+            IdentityTransitionsPlugin::<GameState>::default(),
+            IdentityTransitionsPlugin::<VoteState>::default(),
+        ));
+        app.init_state::<ServerState>();
+        app.add_sub_state::<RoomState>();
+        app.add_sub_state::<GameState>();
+        app.add_sub_state::<VoteState>();
+        app.init_resource::<Users>();
+        app.init_resource::<DefaultGameConfig>();
+        app.init_resource::<WordFilter>();
+        app.init_resource::<PromptBank>();
+        app.init_resource::<ThemeList>();
+        app.init_resource::<HostTheme>();
+        app.init_resource::<MatchSeed>();
+        app.init_resource::<PhaseTimer>();
+        app.init_resource::<PhaseProgress>();
+        app.configure_sets(
+            Update,
+            (
+                GameSystemOdering::Networking,
+                GameSystemOdering::StateLogic,
+                GameSystemOdering::ChangeState,
+            )
+                .chain(),
+        );
+
+        // Debug
+        app.add_systems(
+            Update,
+            (
+                log_transitions::<ServerState>,
+                log_transitions::<RoomState>,
+                log_transitions::<GameState>,
+            )
+                .chain(),
+        );
+
+        // ServerState::Offline
+        app.add_systems(OnEnter(ServerState::Offline), states::setup_server_offline);
+
+        // ServerState::Running
+        app.add_systems(OnEnter(ServerState::Running), states::setup_server_online);
+        app.add_systems(OnExit(ServerState::Running), states::teardown_server_online);
+        app.add_plugins(TransportPlugin);
+        app.add_event::<NetMsg<ClientMsgComm>>();
+        app.add_systems(
+            PreUpdate,
+            (
+                networking::handle_transport_events,
+                networking::handle_root,
+                networking::handle_comm,
+                networking::announce_host,
+            )
+                .chain()
+                .after(TransportSet::Receive)
+                .in_set(GameSystemOdering::Networking)
+                .run_if(in_state(ServerState::Running)),
+        );
+        app.enable_state_scoped_entities::<RoomState>();
+        app.add_event::<HostRequest>();
+        app.add_systems(
+            Update,
+            (timer::handle_host_commands, themes::set_host_theme)
+                .in_set(GameSystemOdering::Networking),
+        );
+        app.add_systems(
+            Update,
+            progress::broadcast_progress
+                .after(GameSystemOdering::StateLogic)
+                .before(GameSystemOdering::ChangeState),
+        );
+
+        // RoomState::Waiting
+        app.add_systems(
+            Update,
+            start_lobby
+                .in_set(GameSystemOdering::ChangeState)
+                .run_if(in_state(RoomState::Waiting)),
+        );
+
+        // RoomState::Running
+        app.add_event::<ProgressGame>();
+        app.add_statebound(
+            RoomState::Running,
+            states::setup_room_running,
+            states::teardown_room_running,
+            (progress_game, stop_lobby).in_set(GameSystemOdering::ChangeState),
+        );
+
+        app.add_plugins((ModesPlugin, ReplayRecorderPlugin));
+    }
+}
+
+#[derive(SystemSet, Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum GameSystemOdering {
+    Networking,
+    StateLogic,
+    ChangeState,
+}
+
+#[derive(Event)]
+pub struct ProgressGame;
+
+fn progress_game(
+    mut progress: ResMut<Events<ProgressGame>>,
+    mut commands: Commands,
+    mut room_next: ResMut<NextState<RoomState>>,
+    mut game_next: ResMut<NextState<GameState>>,
+    mut game_data: ResMut<GameConfig>,
+) {
+    if progress.drain().last().is_none() {
+        return;
+    }
+
+    let Some(next_state) = game_data.next_state() else {
+        room_next.set(RoomState::Waiting);
+        return;
+    };
+
+    match next_state {
+        StateData::Draw(config) => {
+            game_next.set(GameState::Draw);
+            commands.insert_resource(config);
+        }
+        StateData::Prompt(config) => {
+            game_next.set(GameState::Prompt);
+            commands.insert_resource(config);
+        }
+        StateData::Combine(config) => {
+            game_next.set(GameState::Combine);
+            commands.insert_resource(config);
+        }
+        StateData::Vote(config) => {
+            game_next.set(GameState::Vote);
+            commands.insert_resource(config);
+        }
+    };
+}
+
+fn start_lobby(
+    users: Res<Users>,
+    mut room_next: ResMut<NextState<RoomState>>,
+    mut progress: EventWriter<ProgressGame>,
+) {
+    // TODO: Wait for host start instead
+    if users.registered.len() >= 2 {
+        room_next.set(RoomState::Running);
+        progress.send(ProgressGame);
+    }
+}
+
+fn stop_lobby(users: Res<Users>, mut room_next: ResMut<NextState<RoomState>>) {
+    if users.registered.is_empty() {
+        room_next.set(RoomState::Waiting);
+    }
+}
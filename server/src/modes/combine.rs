@@ -1,8 +1,8 @@
-use std::{ops::Range, time::Duration};
+use std::ops::Range;
 
 use bevy::prelude::*;
 use bevy::{ecs::prelude::Resource, utils::hashbrown::HashSet};
-use bevy_quinnet::{server::QuinnetServer, shared::ClientId};
+use bevy_quinnet::shared::ClientId;
 use common::{
     app::AppExt,
     game::{Combination, Combined, Drawing, Index, Indexer, Prompt},
@@ -11,9 +11,14 @@ use common::{
 use rand::prelude::SliceRandom;
 
 use crate::{
+    filter::Flagged,
     game::{CombineConfig, GameConfig},
     networking::Submission,
+    progress::PhaseProgress,
+    rng::GameRng,
     states::{GameState, RoomState},
+    timer::PhaseTimer,
+    transport::Outbox,
     GameSystemOdering, ProgressGame, Users,
 };
 
@@ -33,45 +38,65 @@ impl Plugin for ModePlugin {
 
 #[derive(Resource, Debug)]
 pub struct Context {
-    started: Duration,
     submited: HashSet<ClientId>,
 }
 
 fn setup(
     mut commands: Commands,
-    mut server: ResMut<QuinnetServer>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut rng: ResMut<GameRng>,
+    mut timer: ResMut<PhaseTimer>,
+    mut phase_progress: ResMut<PhaseProgress>,
     time: Res<Time>,
     users: Res<Users>,
     config: Res<CombineConfig>,
     drawings: Query<(Entity, &Index, &Drawing), Without<Prompt>>,
-    prompts: Query<(Entity, &Index, &Prompt), Without<Drawing>>,
+    prompts: Query<(Entity, &Index, &Prompt), (Without<Drawing>, Without<Flagged>)>,
 ) {
     info!("Setup combine");
-    commands.insert_resource(Context {
-        started: time.elapsed(),
-        submited: HashSet::new(),
-    });
-
-    let mut rng = rand::thread_rng();
+    let deadline = timer.start(time.elapsed(), config.duration);
+    phase_progress.start();
+    let rng = &mut **rng;
     let mut drawing_ids = drawings.iter().map(|e| e.0).collect::<Vec<_>>();
-    drawing_ids.shuffle(&mut rng);
+    drawing_ids.shuffle(rng);
     let mut prompt_ids = prompts.iter().map(|e| e.0).collect::<Vec<_>>();
-    prompt_ids.shuffle(&mut rng);
+    prompt_ids.shuffle(rng);
     let mut user_ids = users.iter_active().map(|u| *u.0).collect::<Vec<_>>();
-    user_ids.shuffle(&mut rng);
+    user_ids.sort();
+    user_ids.shuffle(rng);
 
     let drawing_count = drawing_ids.len();
     let prompt_count = prompt_ids.len();
     let user_count = user_ids.len();
 
-    if drawing_count < user_count || prompt_count < user_count {
-        todo!("Handle not enough content!");
+    // Players left over once every drawing or prompt is handed out sit the phase out
+    let sharing = user_count.min(drawing_count).min(prompt_count);
+    let (user_ids, idle_ids) = user_ids.split_at(sharing);
+    let mut submited = HashSet::new();
+    if !idle_ids.is_empty() {
+        warn!(
+            drawing_count,
+            prompt_count, user_count, "Not enough content for every player to combine"
+        );
     }
+    for id in idle_ids {
+        submited.insert(*id);
+        phase_progress.mark_done(*id);
+        let message = ServerMsgRoot::Combine {
+            deadline,
+            drawings: vec![],
+            prompts: vec![],
+        };
+        outbox.send(Outbox::client(*id, message));
+    }
+    commands.insert_resource(Context { submited });
 
-    let min_drawings_per_user = drawing_count / user_count;
-    let extra_drawing_for_first_n_users = drawing_count % user_count;
-    let min_prompts_per_user = prompt_count / user_count;
-    let extra_prompt_for_first_n_users = prompt_count % user_count;
+    // Nobody gets content when there's none to share
+    let sharing = sharing.max(1);
+    let min_drawings_per_user = drawing_count / sharing;
+    let extra_drawing_for_first_n_users = drawing_count % sharing;
+    let min_prompts_per_user = prompt_count / sharing;
+    let extra_prompt_for_first_n_users = prompt_count % sharing;
 
     fn count_for_idx(min: usize, extra: usize, idx: usize) -> usize {
         idx * min + (idx < extra) as usize
@@ -85,8 +110,7 @@ fn setup(
         }
     }
 
-    let endpoint = server.endpoint_mut();
-    for (idx, id) in user_ids.into_iter().enumerate() {
+    for (idx, id) in user_ids.iter().enumerate() {
         let drawing_ids = &drawing_ids
             [range_for_idx(min_drawings_per_user, extra_drawing_for_first_n_users, idx)];
         let drawings = drawings
@@ -102,12 +126,12 @@ fn setup(
             .collect::<Vec<_>>();
 
         let message = ServerMsgRoot::Combine {
-            duration: config.duration,
+            deadline,
             drawings,
             prompts,
         };
 
-        endpoint.send_message(id, &message).ok();
+        outbox.send(Outbox::client(*id, message));
     }
 }
 
@@ -115,13 +139,23 @@ fn update(
     mut commands: Commands,
     mut submissions: ResMut<Events<Submission<Combination>>>,
     mut progress: EventWriter<ProgressGame>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
     mut indexer: ResMut<Indexer>,
     mut context: ResMut<Context>,
+    mut phase_progress: ResMut<PhaseProgress>,
     game_config: Res<GameConfig>,
-    config: Res<CombineConfig>,
+    mut timer: ResMut<PhaseTimer>,
     time: Res<Time>,
     drawings: Query<(Entity, &Index), (With<Drawing>, Without<Prompt>, Without<Combined>)>,
-    prompts: Query<(Entity, &Index), (With<Prompt>, Without<Drawing>, Without<Combined>)>,
+    prompts: Query<
+        (Entity, &Index),
+        (
+            With<Prompt>,
+            Without<Drawing>,
+            Without<Combined>,
+            Without<Flagged>,
+        ),
+    >,
     users: Res<Users>,
 ) {
     for submission in submissions.drain() {
@@ -145,6 +179,7 @@ fn update(
         info!("{:?}", submission);
 
         context.submited.insert(submission.author.id);
+        phase_progress.mark_done(submission.author.id);
         commands.spawn((
             StateScoped(RoomState::Running),
             submission.author,
@@ -154,7 +189,10 @@ fn update(
         commands.entity(drawing.0).insert(Combined);
         commands.entity(prompt.0).insert(Combined);
     }
-    let out_of_time = context.started + config.duration + game_config.extra_time < time.elapsed();
+    if timer.warn(time.elapsed()) {
+        outbox.send(Outbox::active(ServerMsgRoot::PhaseEnding));
+    }
+    let out_of_time = timer.is_over(time.elapsed(), game_config.extra_time);
     let everyone_submitted = context.submited.len() >= users.iter_active().count();
     if out_of_time || everyone_submitted {
         progress.send(ProgressGame);
@@ -1,8 +1,6 @@
-use std::time::Duration;
-
 use bevy::prelude::*;
-use bevy::{ecs::prelude::Resource, utils::hashbrown::HashSet};
-use bevy_quinnet::{server::QuinnetServer, shared::ClientId};
+use bevy::{ecs::prelude::Resource, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
 use common::{
     app::AppExt,
     game::{Drawing, Indexer},
@@ -12,7 +10,12 @@ use common::{
 use crate::{
     game::{DrawConfig, GameConfig},
     networking::Submission,
+    progress::PhaseProgress,
+    rng::GameRng,
     states::{GameState, RoomState},
+    themes::{HostTheme, ThemeList},
+    timer::PhaseTimer,
+    transport::Outbox,
     GameSystemOdering, ProgressGame, Users,
 };
 
@@ -32,49 +35,69 @@ impl Plugin for ModePlugin {
 
 #[derive(Resource, Debug)]
 pub struct Context {
-    started: Duration,
-    submited: HashSet<ClientId>,
+    submited: HashMap<ClientId, usize>,
 }
 
 fn setup(
     mut commands: Commands,
-    mut server: ResMut<QuinnetServer>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut timer: ResMut<PhaseTimer>,
     time: Res<Time>,
-    users: Res<Users>,
+    mut phase_progress: ResMut<PhaseProgress>,
     config: Res<DrawConfig>,
+    themes: Res<ThemeList>,
+    host_theme: Res<HostTheme>,
+    mut rng: ResMut<GameRng>,
 ) {
     info!("Setup draw");
+    let deadline = timer.start(time.elapsed(), config.duration);
+    phase_progress.start();
     commands.insert_resource(Context {
-        started: time.elapsed(),
-        submited: HashSet::new(),
+        submited: HashMap::new(),
     });
-    let endpoint = server.endpoint_mut();
+    // Picked even when the host's theme wins, so the random draws don't depend on it
+    let picked = themes.pick(&config.theme, &mut **rng);
+    let theme = host_theme.0.clone().or(picked);
+    if let Some(theme) = &theme {
+        info!(theme, "Drawing theme");
+    }
     let message = ServerMsgRoot::Draw {
-        duration: config.duration,
+        deadline,
+        drawings: config.drawings_per_player,
+        theme,
     };
-    for (id, _) in users.iter_active() {
-        endpoint.send_message(*id, &message).ok();
-    }
+    outbox.send(Outbox::active(message));
 }
 
 fn update(
     mut commands: Commands,
     mut submissions: ResMut<Events<Submission<Drawing>>>,
     mut progress: EventWriter<ProgressGame>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
     mut indexer: ResMut<Indexer>,
     mut context: ResMut<Context>,
+    mut phase_progress: ResMut<PhaseProgress>,
     game_config: Res<GameConfig>,
     config: Res<DrawConfig>,
+    mut timer: ResMut<PhaseTimer>,
     time: Res<Time>,
     users: Res<Users>,
 ) {
     for submission in submissions.drain() {
-        if context.submited.contains(&submission.author.id) {
-            warn!("User submitting drawing multiple times!");
+        if let Err(err) = submission.data.validate() {
+            warn!(?err, "User submitting invalid drawing!");
+            continue;
+        }
+        let submited = context.submited.entry(submission.author.id).or_default();
+        if *submited >= config.drawings_per_player {
+            warn!("User submitting too many drawings!");
             continue;
         }
         info!("{:?}", submission);
-        context.submited.insert(submission.author.id);
+        *submited += 1;
+        if *submited >= config.drawings_per_player {
+            phase_progress.mark_done(submission.author.id);
+        }
         commands.spawn((
             StateScoped(RoomState::Running),
             submission.author,
@@ -82,8 +105,16 @@ fn update(
             indexer.next(),
         ));
     }
-    let out_of_time = context.started + config.duration + game_config.extra_time < time.elapsed();
-    let everyone_submitted = context.submited.len() >= users.iter_active().count();
+    if timer.warn(time.elapsed()) {
+        outbox.send(Outbox::active(ServerMsgRoot::PhaseEnding));
+    }
+    let out_of_time = timer.is_over(time.elapsed(), game_config.extra_time);
+    let everyone_submitted = users.iter_active().all(|(id, _)| {
+        context
+            .submited
+            .get(id)
+            .is_some_and(|submited| *submited >= config.drawings_per_player)
+    });
     if out_of_time || everyone_submitted {
         progress.send(ProgressGame);
     }
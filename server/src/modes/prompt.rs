@@ -1,18 +1,22 @@
-use std::time::Duration;
-
-use bevy::ecs::prelude::Resource;
 use bevy::prelude::*;
-use bevy_quinnet::server::QuinnetServer;
+use bevy::{ecs::prelude::Resource, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
 use common::{
     app::AppExt,
-    game::{Indexer, Prompt},
-    protocol::ServerMsgRoot,
+    game::{Author, Index, Indexer, Prompt},
+    protocol::{HostCommand, ServerMsgRoot},
 };
 
 use crate::{
+    filter::{Flagged, Verdict, WordFilter},
     game::{GameConfig, PromptConfig},
     networking::Submission,
+    progress::PhaseProgress,
+    prompt_bank::PromptBank,
+    rng::GameRng,
     states::{GameState, RoomState},
+    timer::{HostRequest, PhaseTimer},
+    transport::Outbox,
     GameSystemOdering, ProgressGame, Users,
 };
 
@@ -25,68 +29,208 @@ impl Plugin for ModePlugin {
             GameState::Prompt,
             setup,
             teardown,
-            update.in_set(GameSystemOdering::StateLogic),
+            (review, update)
+                .chain()
+                .in_set(GameSystemOdering::StateLogic),
         );
     }
 }
 
 #[derive(Resource, Debug)]
 pub struct Context {
-    started: Duration,
-    submited: usize,
+    /// Prompts of each player let into the match.
+    submited: HashMap<ClientId, usize>,
+    /// Prompts of each player waiting for the host to review them.
+    held: HashMap<ClientId, usize>,
+}
+
+impl Context {
+    fn approve(&mut self, author: ClientId) {
+        self.release(author);
+        *self.submited.entry(author).or_default() += 1;
+    }
+
+    fn release(&mut self, author: ClientId) {
+        if let Some(held) = self.held.get_mut(&author) {
+            *held = held.saturating_sub(1);
+        }
+    }
 }
 
 fn setup(
     mut commands: Commands,
-    mut server: ResMut<QuinnetServer>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut timer: ResMut<PhaseTimer>,
+    mut phase_progress: ResMut<PhaseProgress>,
     time: Res<Time>,
-    users: Res<Users>,
     config: Res<PromptConfig>,
 ) {
     info!("Setup prompt");
+    let deadline = timer.start(time.elapsed(), config.duration);
+    phase_progress.start();
     commands.insert_resource(Context {
-        started: time.elapsed(),
-        submited: 0,
+        submited: HashMap::new(),
+        held: HashMap::new(),
     });
-    let endpoint = server.endpoint_mut();
     let message = ServerMsgRoot::Prompt {
-        duration: config.duration,
+        deadline,
+        prompts: config.prompts_per_player,
     };
-    for (id, _) in users.iter_active() {
-        endpoint.send_message(*id, &message).ok();
-    }
+    outbox.send(Outbox::active(message));
 }
 
 fn update(
     mut commands: Commands,
     mut submissions: ResMut<Events<Submission<Prompt>>>,
     mut progress: EventWriter<ProgressGame>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
     mut indexer: ResMut<Indexer>,
     mut context: ResMut<Context>,
+    mut phase_progress: ResMut<PhaseProgress>,
     game_config: Res<GameConfig>,
     config: Res<PromptConfig>,
+    filter: Res<WordFilter>,
+    bank: Res<PromptBank>,
+    mut rng: ResMut<GameRng>,
+    mut timer: ResMut<PhaseTimer>,
     time: Res<Time>,
     users: Res<Users>,
+    flagged: Query<(Entity, &Author), (With<Prompt>, With<Flagged>)>,
 ) {
-    let user_count = users.iter_active().count();
-    let max_submissions = user_count * config.prompts_per_player;
-    for submission in submissions.drain() {
+    for mut submission in submissions.drain() {
+        let author = submission.author.id;
+        let submited = context.submited.get(&author).copied().unwrap_or_default();
+        let held = context.held.get(&author).copied().unwrap_or_default();
+        if submited + held >= config.prompts_per_player {
+            warn!("User submitting too many prompts!");
+            continue;
+        }
+        let flagged = match filter.check(&submission.data.text) {
+            Verdict::Clean => false,
+            Verdict::Masked(text) => {
+                submission.data.text = text;
+                false
+            }
+            Verdict::Rejected => {
+                warn!("Prompt rejected by word filter!");
+                let message = ServerMsgRoot::PromptRejected(submission.data);
+                outbox.send(Outbox::client(author, message));
+                continue;
+            }
+            Verdict::Review => {
+                warn!("Prompt held back for host review!");
+                true
+            }
+        };
         info!("{:?}", submission);
-        context.submited += 1;
-        commands.spawn((
+        let index = indexer.next();
+        if flagged {
+            *context.held.entry(author).or_default() += 1;
+            if let Some(host) = users.host {
+                let message = ServerMsgRoot::PromptReview {
+                    prompt: (index, submission.data.clone()),
+                    author: submission.author.name.clone(),
+                };
+                outbox.send(Outbox::client(host, message));
+            }
+        } else {
+            context.approve(author);
+            if context.submited[&author] >= config.prompts_per_player {
+                phase_progress.mark_done(author);
+            }
+        }
+        let mut entity = commands.spawn((
             StateScoped(RoomState::Running),
             submission.author,
             submission.data,
-            indexer.next(),
+            index,
         ));
+        if flagged {
+            entity.insert(Flagged);
+        }
+    }
+    if timer.warn(time.elapsed()) {
+        outbox.send(Outbox::active(ServerMsgRoot::PhaseEnding));
     }
-    let out_of_time = context.started + config.duration + game_config.extra_time < time.elapsed();
-    let everyone_submitted = max_submissions <= context.submited;
+    let out_of_time = timer.is_over(time.elapsed(), game_config.extra_time);
+    let missing = |context: &Context| {
+        users
+            .iter_active()
+            .map(|(id, _)| {
+                let submited = context.submited.get(id).copied().unwrap_or_default();
+                config.prompts_per_player.saturating_sub(submited)
+            })
+            .sum::<usize>()
+    };
+    let everyone_submitted = missing(&context) == 0;
     if out_of_time || everyone_submitted {
+        // Prompts the host didn't get to, or that no host was there to review, are let in
+        for (entity, author) in &flagged {
+            info!(?entity, "Held back prompt let in without review");
+            commands.entity(entity).remove::<Flagged>();
+            context.approve(author.id);
+        }
+        let missing = missing(&context);
+        let max_submissions = users.iter_active().count() * config.prompts_per_player;
+        let house = (max_submissions as f32 * config.house_ratio).round() as usize;
+        let count = house.max(missing);
+        let prompts = bank.draw(config.house_category.as_deref(), count, &mut **rng);
+        if prompts.len() < count {
+            warn!(
+                requested = count,
+                available = prompts.len(),
+                "Not enough house prompts!"
+            );
+        }
+        for prompt in prompts {
+            commands.spawn((
+                StateScoped(RoomState::Running),
+                PromptBank::author(),
+                prompt,
+                indexer.next(),
+            ));
+        }
         progress.send(ProgressGame);
     }
 }
 
+/// Apply the host's review of held back prompts.
+fn review(
+    mut commands: Commands,
+    mut requests: EventReader<HostRequest>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut context: ResMut<Context>,
+    mut phase_progress: ResMut<PhaseProgress>,
+    config: Res<PromptConfig>,
+    flagged: Query<(Entity, &Index, &Author, &Prompt), With<Flagged>>,
+) {
+    for HostRequest(command) in requests.read() {
+        let (HostCommand::ApprovePrompt(index) | HostCommand::RejectPrompt(index)) = *command
+        else {
+            continue;
+        };
+        let Some((entity, _, author, prompt)) = flagged.iter().find(|(_, i, ..)| **i == index)
+        else {
+            warn!(?index, "Reviewed prompt isn't held back!");
+            continue;
+        };
+        if let HostCommand::ApprovePrompt(_) = command {
+            info!(?prompt, "Prompt approved by the host");
+            commands.entity(entity).remove::<Flagged>();
+            context.approve(author.id);
+            if context.submited[&author.id] >= config.prompts_per_player {
+                phase_progress.mark_done(author.id);
+            }
+        } else {
+            info!(?prompt, "Prompt rejected by the host");
+            commands.entity(entity).despawn();
+            context.release(author.id);
+            let message = ServerMsgRoot::PromptRejected(prompt.clone());
+            outbox.send(Outbox::client(author.id, message));
+        }
+    }
+}
+
 fn teardown(mut commands: Commands) {
     commands.remove_resource::<PromptConfig>();
     commands.remove_resource::<Context>();
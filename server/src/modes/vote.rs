@@ -1,18 +1,29 @@
-use std::time::Duration;
+use std::{cmp::Ordering, time::Duration};
 
 use bevy::prelude::*;
-use bevy::{ecs::prelude::Resource, utils::hashbrown::HashSet};
-use bevy_quinnet::{server::QuinnetServer, shared::ClientId};
+use bevy::{
+    ecs::prelude::Resource,
+    utils::hashbrown::{HashMap, HashSet},
+};
+use bevy_quinnet::shared::ClientId;
 use common::{
-    game::{Combination, Drawing, Index, Prompt, Vote, VotedOut},
+    game::{
+        Author, Combination, Credits, Drawing, Index, Prompt, RevealStage, Vote, VoteResult,
+        VoteTally, VotedOut,
+    },
     protocol::ServerMsgRoot,
 };
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, Rng};
 
 use crate::{
-    game::{GameConfig, VoteConfig},
+    game::{GameConfig, LiveTally, VoteConfig},
     networking::Submission,
+    progress::PhaseProgress,
+    rng::GameRng,
+    scores::Scores,
     states::{GameState, VoteState},
+    timer::PhaseTimer,
+    transport::Outbox,
     GameSystemOdering, ProgressGame, Users,
 };
 
@@ -28,7 +39,7 @@ impl Plugin for ModePlugin {
         app.add_systems(
             Update,
             update_voting
-                .run_if(in_state(VoteState::Voting))
+                .run_if(in_state(VoteState::Voting).and_then(resource_exists::<VotingContext>))
                 .in_set(GameSystemOdering::StateLogic),
         );
         app.add_systems(OnExit(VoteState::Voting), teardown_voting);
@@ -44,29 +55,63 @@ impl Plugin for ModePlugin {
     }
 }
 
+/// Points every author of the winner earns per vote.
+const POINTS_PER_VOTE: u32 = 100;
+
 #[derive(Resource, Debug)]
 pub struct VotingContext {
-    started: Duration,
-    submited: HashSet<ClientId>,
+    /// Latest vote of each player, which can change until the deadline.
+    votes: HashMap<ClientId, Index>,
     combination1: (Entity, Index),
-    combination1_votes: usize,
     combination2: (Entity, Index),
-    combination2_votes: usize,
+    /// Last live tally sent to players.
+    sent_tally: Option<VoteTally>,
 }
 
+impl VotingContext {
+    fn tally(&self, players: usize, live_tally: LiveTally) -> VoteTally {
+        let votes_for = |index| self.votes.values().filter(|vote| **vote == index).count();
+        VoteTally {
+            voted: self.votes.len(),
+            players,
+            votes: (live_tally == LiveTally::Counts).then(|| {
+                (
+                    votes_for(self.combination1.1),
+                    votes_for(self.combination2.1),
+                )
+            }),
+        }
+    }
+}
+
+/// Next part of the vote result to reveal.
 #[derive(Resource, Debug)]
-pub struct WinnerContext {
-    started: Duration,
+pub struct RevealTimeline {
+    next: Option<RevealStage>,
 }
 
 #[derive(Event)]
-pub struct Winner(Entity);
+pub struct Winner {
+    winner: Tally,
+    loser: Tally,
+}
+
+/// Combination on one side of a vote, with who voted for it.
+pub struct Tally {
+    combination: Entity,
+    votes: usize,
+    /// Names of the voters still in the room.
+    voters: Vec<String>,
+}
 
 fn setup_voting(
     mut commands: Commands,
-    mut server: ResMut<QuinnetServer>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut progress: EventWriter<ProgressGame>,
+    mut rng: ResMut<GameRng>,
+    mut timer: ResMut<PhaseTimer>,
+    mut phase_progress: ResMut<PhaseProgress>,
     time: Res<Time>,
-    users: Res<Users>,
     config: Res<VoteConfig>,
     combinations: Query<
         (Entity, &Index, &Combination),
@@ -81,18 +126,20 @@ fn setup_voting(
     prompts: Query<(&Index, &Prompt), (Without<Combination>, Without<Drawing>)>,
 ) {
     info!("Setup vote");
-    let mut rng = rand::thread_rng();
     let mut combinations = combinations.iter().collect::<Vec<_>>();
-    combinations.shuffle(&mut rng);
-    let combination1 = combinations[0];
-    let combination2 = combinations[1];
+    combinations.shuffle(&mut **rng);
+    let [combination1, combination2, ..] = combinations[..] else {
+        warn!("Not enough combinations to vote on");
+        progress.send(ProgressGame);
+        return;
+    };
+    let deadline = timer.start(time.elapsed(), config.voting_duration);
+    phase_progress.start();
     commands.insert_resource(VotingContext {
-        started: time.elapsed(),
-        submited: HashSet::new(),
+        votes: HashMap::new(),
         combination1: (combination1.0, *combination1.1),
         combination2: (combination2.0, *combination2.1),
-        combination1_votes: 0,
-        combination2_votes: 0,
+        sent_tally: None,
     });
     let drawing1 = drawings
         .iter()
@@ -118,15 +165,12 @@ fn setup_voting(
         .unwrap()
         .1
         .clone();
-    let endpoint = server.endpoint_mut();
     let message = ServerMsgRoot::Vote {
-        duration: config.voting_duration,
+        deadline,
         combination1: (*combination1.1, drawing1, prompt1),
         combination2: (*combination2.1, drawing2, prompt2),
     };
-    for (id, _) in users.iter_active() {
-        endpoint.send_message(*id, &message).ok();
-    }
+    outbox.send(Outbox::active(message));
 }
 
 fn update_voting(
@@ -134,104 +178,170 @@ fn update_voting(
     mut submissions: ResMut<Events<Submission<Vote>>>,
     mut next: ResMut<NextState<VoteState>>,
     mut context: ResMut<VotingContext>,
+    mut phase_progress: ResMut<PhaseProgress>,
     mut winner: EventWriter<Winner>,
-    game_config: Res<GameConfig>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut rng: ResMut<GameRng>,
     config: Res<VoteConfig>,
+    game_config: Res<GameConfig>,
+    timer: Res<PhaseTimer>,
     time: Res<Time>,
     users: Res<Users>,
 ) {
     for submission in submissions.drain() {
-        if context.submited.contains(&submission.author.id) {
-            warn!("User submitting vote multiple times!");
-            continue;
-        }
-        info!("{:?}", submission);
-        context.submited.insert(submission.author.id);
         if submission.data.combination != context.combination1.1
             && submission.data.combination != context.combination2.1
         {
             warn!("User submitting invalid vote!");
             continue;
         }
-        if submission.data.combination == context.combination1.1 {
-            context.combination1_votes += 1;
-        } else {
-            context.combination2_votes += 1;
+        info!("{:?}", submission);
+        let previous = context
+            .votes
+            .insert(submission.author.id, submission.data.combination);
+        if previous.is_some() {
+            info!("User changed their vote");
         }
+        phase_progress.mark_done(submission.author.id);
     }
-    let out_of_time =
-        context.started + config.voting_duration + game_config.extra_time < time.elapsed();
-    let everyone_submitted = context.submited.len() >= users.iter_active().count();
-    if out_of_time || everyone_submitted {
-        if context.combination1_votes >= context.combination2_votes {
-            commands.entity(context.combination2.0).insert(VotedOut);
-            winner.send(Winner(context.combination1.0));
-        } else {
-            commands.entity(context.combination1.0).insert(VotedOut);
-            winner.send(Winner(context.combination2.0));
+
+    if config.live_tally != LiveTally::Off {
+        let tally = context.tally(users.iter_active().count(), config.live_tally);
+        if context.sent_tally.as_ref() != Some(&tally) {
+            outbox.send(Outbox::active(ServerMsgRoot::Tally(tally.clone())));
+            context.sent_tally = Some(tally);
         }
+    }
+
+    // Players can change their mind, so voting always lasts until the deadline
+    let out_of_time = timer.is_over(time.elapsed(), game_config.extra_time);
+    if out_of_time {
+        let tally = |(combination, index): (Entity, Index)| {
+            let mut voters = context
+                .votes
+                .iter()
+                .filter(|(_, vote)| **vote == index)
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            voters.sort();
+            Tally {
+                combination,
+                votes: voters.len(),
+                voters: voters
+                    .iter()
+                    .filter_map(|id| users.registered.get(id))
+                    .map(|user| user.name.clone())
+                    .collect(),
+            }
+        };
+        let first = tally(context.combination1);
+        let second = tally(context.combination2);
+        let first_wins = match first.votes.cmp(&second.votes) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => rng.gen(),
+        };
+        let (winning, losing) = if first_wins {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        commands.entity(losing.combination).insert(VotedOut);
+        winner.send(Winner {
+            winner: winning,
+            loser: losing,
+        });
         next.set(VoteState::Winner);
     }
 }
 
 fn setup_winner(
     mut commands: Commands,
-    mut server: ResMut<QuinnetServer>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut timer: ResMut<PhaseTimer>,
+    mut phase_progress: ResMut<PhaseProgress>,
+    mut scores: ResMut<Scores>,
     time: Res<Time>,
-    users: Res<Users>,
     config: Res<VoteConfig>,
     combinations: Query<
-        &Combination,
-        (
-            With<Combination>,
-            Without<VotedOut>,
-            Without<Drawing>,
-            Without<Prompt>,
-        ),
+        (&Combination, &Author),
+        (With<Combination>, Without<Drawing>, Without<Prompt>),
     >,
-    drawings: Query<(&Index, &Drawing), (Without<Combination>, Without<Prompt>)>,
-    prompts: Query<(&Index, &Prompt), (Without<Combination>, Without<Drawing>)>,
+    drawings: Query<(&Index, &Drawing, &Author), (Without<Combination>, Without<Prompt>)>,
+    prompts: Query<(&Index, &Prompt, &Author), (Without<Combination>, Without<Drawing>)>,
     mut winner: ResMut<Events<Winner>>,
 ) {
-    let winner = winner.drain().last().unwrap();
-    commands.insert_resource(WinnerContext {
-        started: time.elapsed(),
+    let Winner { winner, loser } = winner.drain().last().unwrap();
+    let deadline = timer.start(time.elapsed(), config.winner_duration);
+    phase_progress.stop();
+    commands.insert_resource(RevealTimeline {
+        next: Some(RevealStage::FIRST),
     });
 
-    let combination = combinations.get(winner.0).unwrap();
-    let drawing = drawings
-        .iter()
-        .find(|d| *d.0 == combination.drawing)
-        .unwrap()
-        .1
-        .clone();
-    let prompt = prompts
-        .iter()
-        .find(|d| *d.0 == combination.prompt)
-        .unwrap()
-        .1
-        .clone();
+    // Result of a side, with the ids of its authors
+    let result = |tally: Tally| {
+        let (combination, combiner) = combinations.get(tally.combination).unwrap();
+        let (_, drawing, artist) = drawings
+            .iter()
+            .find(|d| *d.0 == combination.drawing)
+            .unwrap();
+        let (_, prompt, writer) = prompts.iter().find(|d| *d.0 == combination.prompt).unwrap();
+        let result = VoteResult {
+            drawing: drawing.clone(),
+            prompt: prompt.clone(),
+            credits: Credits {
+                artist: artist.name.clone(),
+                writer: writer.name.clone(),
+                combiner: combiner.name.clone(),
+            },
+            votes: tally.votes,
+            voters: if config.anonymous {
+                vec![]
+            } else {
+                tally.voters
+            },
+        };
+        (result, [artist.id, writer.id, combiner.id])
+    };
+    let (winner, authors) = result(winner);
+    let (loser, _) = result(loser);
+
+    // Players with several parts in the winner only score once
+    let points = winner.votes as u32 * POINTS_PER_VOTE;
+    let authors: HashSet<_> = authors.into_iter().collect();
+    for id in authors {
+        scores.add(id, points);
+    }
 
-    let endpoint = server.endpoint_mut();
     let message = ServerMsgRoot::Winner {
-        duration: config.winner_duration,
-        drawing,
-        prompt,
+        deadline,
+        winner: Box::new(winner),
+        loser: Box::new(loser),
+        points,
     };
-    for (id, _) in users.iter_active() {
-        endpoint.send_message(*id, &message).ok();
-    }
+    outbox.send(Outbox::active(message));
 }
 
 fn update_winner(
     mut progress: EventWriter<ProgressGame>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
     mut next: ResMut<NextState<VoteState>>,
+    mut reveal: ResMut<RevealTimeline>,
     combinations: Query<(&Index, &Combination), Without<VotedOut>>,
-    context: Res<WinnerContext>,
     config: Res<VoteConfig>,
+    timer: Res<PhaseTimer>,
     time: Res<Time>,
 ) {
-    let out_of_time = context.started + config.voting_duration < time.elapsed();
+    let elapsed = timer.elapsed(time.elapsed());
+    while let Some(stage) = reveal
+        .next
+        .filter(|stage| config.reveal.at(*stage) <= elapsed)
+    {
+        outbox.send(Outbox::active(ServerMsgRoot::Reveal(stage)));
+        reveal.next = stage.next();
+    }
+
+    let out_of_time = timer.is_over(time.elapsed(), Duration::ZERO);
     if out_of_time {
         let combinations_left = combinations.iter().len();
         if combinations_left > 2 {
@@ -251,5 +361,5 @@ fn teardown_voting(mut commands: Commands) {
 }
 
 fn teardown_winner(mut commands: Commands) {
-    commands.remove_resource::<WinnerContext>();
+    commands.remove_resource::<RevealTimeline>();
 }
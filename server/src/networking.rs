@@ -1,15 +1,12 @@
-use crate::states::GameState;
+use crate::{
+    states::GameState,
+    timer::HostRequest,
+    transport::{Kick, Outbox, TransportEvent},
+};
 
 use super::Users;
 use bevy::prelude::*;
 use bevy_quinnet::shared::ClientId;
-use bevy_quinnet::{
-    server::{
-        certificate::CertificateRetrievalMode, ConnectionEvent, ConnectionLostEvent, Endpoint,
-        QuinnetServer, ServerEndpointConfiguration,
-    },
-    shared::channels::ChannelsConfiguration,
-};
 use common::{
     game::{Author, Combination, Vote},
     protocol::NetMsg,
@@ -20,63 +17,33 @@ use common::{
 };
 use std::time::Duration;
 
-pub fn start_server(server: &mut QuinnetServer) {
-    server
-        .start_endpoint(
-            ServerEndpointConfiguration::from_string("0.0.0.0:6000").unwrap(),
-            CertificateRetrievalMode::GenerateSelfSigned {
-                server_hostname: "ApeBox sp. Zloo".to_string(),
-            },
-            ChannelsConfiguration::default(),
-        )
-        .unwrap();
-}
-
-pub fn stop_server(server: &mut QuinnetServer) {
-    server.stop_endpoint().ok();
-}
-
-pub fn handle_server_events(
-    mut connection: EventReader<ConnectionEvent>,
-    mut connection_lost: EventReader<ConnectionLostEvent>,
+pub fn handle_transport_events(
+    mut events: EventReader<TransportEvent>,
     mut users: ResMut<Users>,
-    mut server: ResMut<QuinnetServer>,
+    mut kick: EventWriter<Kick>,
     time: Res<Time>,
 ) {
     let now = time.elapsed();
-    for client in connection.read() {
-        users.add_pending(client.id, now);
-    }
-    for client in connection_lost.read() {
-        handle_disconnect(&mut users, client.id, None, "Connection lost");
+    for event in events.read() {
+        match *event {
+            TransportEvent::Connected(id) => users.add_pending(id, now),
+            TransportEvent::Lost(id) => handle_disconnect(&mut users, id, None, "Connection lost"),
+        }
     }
-    let endpoint = server.endpoint_mut();
     for id in users.drain_pending_too_long(Duration::from_secs(3), now) {
-        handle_disconnect(&mut users, id, Some(endpoint), "Pending too long");
-    }
-}
-
-pub fn receive_messages(
-    mut server: ResMut<QuinnetServer>,
-    mut sink: EventWriter<NetMsg<ClientMsgRoot>>,
-) {
-    let endpoint = server.endpoint_mut();
-    for client in endpoint.clients() {
-        while let Some((channel, data)) = endpoint.try_receive_message_from::<ClientMsgRoot>(client)
-        {
-            let net_msg = NetMsg::new(client, channel, data);
-            sink.send(net_msg);
-        }
+        handle_disconnect(&mut users, id, Some(&mut kick), "Pending too long");
     }
 }
 
 pub fn handle_root(
     mut source: ResMut<Events<NetMsg<ClientMsgRoot>>>,
     mut sink: EventWriter<NetMsg<ClientMsgComm>>,
-    mut server: ResMut<QuinnetServer>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut kick: EventWriter<Kick>,
+    mut host: EventWriter<HostRequest>,
     mut users: ResMut<Users>,
+    time: Res<Time>,
 ) {
-    let endpoint = server.endpoint_mut();
     for NetMsg {
         client,
         channel,
@@ -91,13 +58,13 @@ pub fn handle_root(
                     handle_disconnect(
                         &mut users,
                         client,
-                        Some(endpoint),
+                        Some(&mut kick),
                         "Duplicate client id or name",
                     );
                 } else {
                     info!(client, name, "Client active.");
                     users.register(client, name);
-                    endpoint.send_message(client, ServerMsgRoot::Wait).unwrap();
+                    outbox.send(Outbox::client(client, ServerMsgRoot::Wait));
                 }
             }
             ClientMsgRoot::Comm(comm) => {
@@ -105,21 +72,51 @@ pub fn handle_root(
                     handle_disconnect(
                         &mut users,
                         client,
-                        Some(endpoint),
+                        Some(&mut kick),
                         "Non-registered user attempted communication",
                     );
                     continue;
                 }
                 sink.send(NetMsg::new(client, channel, comm));
             }
+            ClientMsgRoot::Ping { sent } => {
+                let pong = ServerMsgRoot::Pong {
+                    sent,
+                    server_time: time.elapsed(),
+                };
+                outbox.send(Outbox::client(client, pong));
+            }
+            ClientMsgRoot::Host(command) => {
+                if !users.is_host(client) {
+                    warn!(client, "Non-host user attempted a host command");
+                    continue;
+                }
+                host.send(HostRequest(command));
+            }
             ClientMsgRoot::Disconnect => {
                 info!(client, "Client disconnected.");
-                handle_disconnect(&mut users, client, Some(endpoint), "Disconnected");
+                handle_disconnect(&mut users, client, Some(&mut kick), "Disconnected");
             }
         }
     }
 }
 
+/// Tell a client when they become the host.
+pub fn announce_host(
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    mut announced: Local<Option<ClientId>>,
+    users: Res<Users>,
+) {
+    if users.host == *announced {
+        return;
+    }
+    *announced = users.host;
+    if let Some(host) = users.host {
+        info!(host, "New host");
+        outbox.send(Outbox::client(host, ServerMsgRoot::Host));
+    }
+}
+
 #[derive(Event, Debug)]
 pub struct Submission<T> {
     pub author: Author,
@@ -147,7 +144,10 @@ pub fn handle_comm(
     };
     for NetMsg { client, data, .. } in source.drain() {
         let state = state.get();
-        let user = users.registered.get(&client).unwrap();
+        // Sent just before disconnecting
+        let Some(user) = users.registered.get(&client) else {
+            continue;
+        };
         let author = Author {
             id: client,
             name: user.name.clone(),
@@ -184,7 +184,7 @@ pub fn handle_comm(
 pub fn handle_disconnect(
     users: &mut Users,
     id: ClientId,
-    endpoint: Option<&mut Endpoint>,
+    kick: Option<&mut EventWriter<Kick>>,
     cause: &'static str,
 ) {
     if let Some(user) = users.remove(&id) {
@@ -192,7 +192,7 @@ pub fn handle_disconnect(
     } else {
         info!(id, cause, "Client disconnected.");
     }
-    if let Some(endpoint) = endpoint {
-        endpoint.disconnect_client(id).ok();
+    if let Some(kick) = kick {
+        kick.send(Kick(id));
     }
 }
@@ -0,0 +1,69 @@
+//! Which players are done with the current phase, shown to everyone.
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_quinnet::shared::ClientId;
+use common::{game::SubmissionProgress, protocol::ServerMsgRoot};
+
+use crate::{transport::Outbox, users::Users};
+
+/// Players done submitting in the current phase.
+///
+/// Modes mark players as their quota is met, changes are sent to players at the end of the frame.
+#[derive(Resource, Debug, Default)]
+pub struct PhaseProgress {
+    /// Whether the current phase takes submissions.
+    tracking: bool,
+    done: HashSet<ClientId>,
+    /// Last progress sent to players.
+    sent: Option<SubmissionProgress>,
+}
+
+impl PhaseProgress {
+    /// Start tracking a new phase, with nobody done.
+    pub fn start(&mut self) {
+        *self = Self {
+            tracking: true,
+            ..default()
+        };
+    }
+
+    /// Stop sending progress, for phases without submissions.
+    pub fn stop(&mut self) {
+        self.tracking = false;
+    }
+
+    pub fn mark_done(&mut self, id: ClientId) {
+        self.done.insert(id);
+    }
+
+    /// Names of active players, split by whether they're done.
+    fn progress(&self, users: &Users) -> SubmissionProgress {
+        let mut progress = SubmissionProgress::default();
+        for (id, user) in users.iter_active() {
+            let names = if self.done.contains(id) {
+                &mut progress.done
+            } else {
+                &mut progress.waiting
+            };
+            names.push(user.name.clone());
+        }
+        progress.done.sort();
+        progress.waiting.sort();
+        progress
+    }
+}
+
+pub fn broadcast_progress(
+    mut progress: ResMut<PhaseProgress>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    users: Res<Users>,
+) {
+    if !progress.tracking {
+        return;
+    }
+    let current = progress.progress(&users);
+    if progress.sent.as_ref() != Some(&current) {
+        outbox.send(Outbox::active(ServerMsgRoot::Progress(current.clone())));
+        progress.sent = Some(current);
+    }
+}
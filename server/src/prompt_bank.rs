@@ -0,0 +1,131 @@
+//! House prompts that supplement player prompts.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use bevy::prelude::*;
+use bevy_quinnet::shared::ClientId;
+use common::game::{Author, CustomFont, Prompt, FONT_COUNT};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+/// Default location of the prompt bank, relative to the working directory.
+pub const PROMPT_BANK_PATH: &str = "prompt_bank.txt";
+
+/// Client id no connection receives, used for house content.
+pub const HOUSE_ID: ClientId = 0;
+
+/// Category for prompts listed before any category header.
+pub const DEFAULT_CATEGORY: &str = "general";
+
+/// Prompts grouped by category.
+///
+/// The file format is one prompt per line, `#` starts a comment.
+/// A `[category]` line puts the prompts that follow into that category.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PromptBank {
+    pub categories: BTreeMap<String, Vec<String>>,
+}
+
+impl PromptBank {
+    /// Pick up to `count` distinct prompts with random fonts.
+    /// Picks from every category if none is given.
+    pub fn draw(&self, category: Option<&str>, count: usize, rng: &mut impl Rng) -> Vec<Prompt> {
+        let pool = match category {
+            Some(category) => self
+                .categories
+                .get(category)
+                .into_iter()
+                .flatten()
+                .collect(),
+            None => self.categories.values().flatten().collect::<Vec<_>>(),
+        };
+        let texts = pool
+            .choose_multiple(rng, count)
+            .map(|text| (*text).clone())
+            .collect::<Vec<_>>();
+        texts
+            .into_iter()
+            .map(|text| Prompt {
+                text,
+                font: CustomFont(rng.gen_range(0..FONT_COUNT)),
+            })
+            .collect()
+    }
+
+    /// Author credited for house prompts.
+    pub fn author() -> Author {
+        Author {
+            id: HOUSE_ID,
+            name: "House".to_owned(),
+        }
+    }
+}
+
+impl FromStr for PromptBank {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bank = PromptBank::default();
+        let mut category = DEFAULT_CATEGORY.to_owned();
+        for line in s.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let name = header
+                    .strip_suffix(']')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| format!("Invalid category header: {line}"))?;
+                category = name.to_owned();
+                continue;
+            }
+            bank.categories
+                .entry(category.clone())
+                .or_default()
+                .push(line.to_owned());
+        }
+        Ok(bank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn parses_categories() {
+        let bank = "Loose prompt\n# comment\n[animals]\nA cat  # trailing\nA dog\n\n[food]\nSoup\n"
+            .parse::<PromptBank>()
+            .unwrap();
+        assert_eq!(bank.categories[DEFAULT_CATEGORY], ["Loose prompt"]);
+        assert_eq!(bank.categories["animals"], ["A cat", "A dog"]);
+        assert_eq!(bank.categories["food"], ["Soup"]);
+        assert!("[animals".parse::<PromptBank>().is_err());
+        assert!("[ ]".parse::<PromptBank>().is_err());
+    }
+
+    #[test]
+    fn draws_distinct_prompts() {
+        let bank = "[animals]\nA cat\nA dog\n[food]\nSoup\n"
+            .parse::<PromptBank>()
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut texts = bank
+            .draw(Some("animals"), 5, &mut rng)
+            .into_iter()
+            .map(|p| p.text)
+            .collect::<Vec<_>>();
+        texts.sort();
+        assert_eq!(texts, ["A cat", "A dog"]);
+
+        let prompts = bank.draw(None, 3, &mut rng);
+        assert_eq!(prompts.len(), 3);
+        assert!(prompts.iter().all(|p| p.font.0 < FONT_COUNT));
+        assert!(bank.draw(Some("cars"), 1, &mut rng).is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! Match recording.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+use common::{
+    protocol::{ClientMsgRoot, NetMsg, ServerMsgRoot},
+    replay::{Replay, ReplayEntry, ReplayEvent},
+};
+
+use crate::{
+    filter::WordFilter,
+    game::GameConfig,
+    prompt_bank::PromptBank,
+    rng::GameRng,
+    states::{self, RoomState},
+    themes::ThemeList,
+    transport::{TransportEvent, TransportSet},
+    users::Users,
+    GameSystemOdering,
+};
+
+pub struct ReplayRecorderPlugin;
+
+impl Plugin for ReplayRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplaySettings>();
+        app.add_systems(
+            OnEnter(RoomState::Running),
+            start_recording.after(states::setup_room_running),
+        );
+        app.add_systems(OnExit(RoomState::Running), save_recording);
+        app.add_systems(
+            PreUpdate,
+            record_inbound
+                .after(TransportSet::Receive)
+                .before(GameSystemOdering::Networking),
+        );
+        app.add_systems(PostUpdate, record_outbound.in_set(TransportSet::Send));
+    }
+}
+
+/// Where to save replays, matches aren't recorded if not set.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ReplaySettings {
+    pub record_dir: Option<PathBuf>,
+}
+
+/// Replay of the running match.
+#[derive(Resource, Debug)]
+struct Recording {
+    started: Duration,
+    replay: Replay,
+}
+
+impl Recording {
+    fn push(&mut self, now: Duration, event: ReplayEvent) {
+        self.replay.entries.push(ReplayEntry {
+            time: now - self.started,
+            event,
+        });
+    }
+}
+
+fn start_recording(
+    mut commands: Commands,
+    settings: Res<ReplaySettings>,
+    config: Res<GameConfig>,
+    filter: Res<WordFilter>,
+    bank: Res<PromptBank>,
+    themes: Res<ThemeList>,
+    rng: Res<GameRng>,
+    users: Res<Users>,
+    time: Res<Time>,
+) {
+    if settings.record_dir.is_none() {
+        return;
+    }
+    let mut players = users
+        .iter_active()
+        .map(|(id, user)| (*id, user.name.clone()))
+        .collect::<Vec<_>>();
+    players.sort();
+    commands.insert_resource(Recording {
+        started: time.elapsed(),
+        replay: Replay {
+            seed: rng.seed(),
+            started: time.elapsed(),
+            config: serde_json::to_string(&*config).unwrap(),
+            word_filter: serde_json::to_string(&*filter).unwrap(),
+            prompt_bank: serde_json::to_string(&*bank).unwrap(),
+            themes: serde_json::to_string(&*themes).unwrap(),
+            players,
+            entries: vec![],
+        },
+    });
+}
+
+fn save_recording(
+    mut commands: Commands,
+    recording: Option<Res<Recording>>,
+    settings: Res<ReplaySettings>,
+) {
+    commands.remove_resource::<Recording>();
+    let (Some(recording), Some(dir)) = (recording, &settings.record_dir) else {
+        return;
+    };
+    let started = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = dir.join(format!("{}-{:016x}.replay", started, recording.replay.seed));
+    let result = std::fs::create_dir_all(dir).and_then(|_| recording.replay.save(&path));
+    match result {
+        Ok(()) => info!(path = %path.display(), "Replay saved"),
+        Err(err) => error!(path = %path.display(), %err, "Failed to save replay"),
+    }
+}
+
+// Recording systems always run, so their readers don't pick up messages from before the match.
+
+fn record_inbound(
+    recording: Option<ResMut<Recording>>,
+    mut transport: EventReader<TransportEvent>,
+    mut messages: EventReader<NetMsg<ClientMsgRoot>>,
+    time: Res<Time>,
+) {
+    let Some(mut recording) = recording else {
+        transport.clear();
+        messages.clear();
+        return;
+    };
+    let now = time.elapsed();
+    for event in transport.read() {
+        if let TransportEvent::Lost(client) = event {
+            recording.push(now, ReplayEvent::Lost(*client));
+        }
+    }
+    for message in messages.read() {
+        let event = ReplayEvent::Inbound(message.client, message.data.clone());
+        recording.push(now, event);
+    }
+}
+
+fn record_outbound(
+    recording: Option<ResMut<Recording>>,
+    mut messages: EventReader<NetMsg<ServerMsgRoot>>,
+    time: Res<Time>,
+) {
+    let Some(mut recording) = recording else {
+        messages.clear();
+        return;
+    };
+    let now = time.elapsed();
+    for message in messages.read() {
+        let event = ReplayEvent::Outbound(message.client, message.data.clone());
+        recording.push(now, event);
+    }
+}
@@ -0,0 +1,29 @@
+//! Randomness of a match.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Seed for every match, each one gets a random seed if not set.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MatchSeed(pub Option<u64>);
+
+/// Source of all randomness during a match, so it can be reproduced from the seed.
+#[derive(Resource, Debug, Deref, DerefMut)]
+pub struct GameRng {
+    seed: u64,
+    #[deref]
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
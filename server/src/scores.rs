@@ -0,0 +1,18 @@
+//! Points players earn over a match.
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
+
+/// Points earned so far in the running match.
+#[derive(Resource, Debug, Default)]
+pub struct Scores(HashMap<ClientId, u32>);
+
+impl Scores {
+    pub fn add(&mut self, id: ClientId, points: u32) {
+        *self.0.entry(id).or_default() += points;
+    }
+
+    pub fn get(&self, id: ClientId) -> u32 {
+        self.0.get(&id).copied().unwrap_or_default()
+    }
+}
@@ -1,8 +1,12 @@
 use bevy::prelude::*;
-use bevy_quinnet::server::{ConnectionEvent, ConnectionLostEvent, QuinnetServer};
 use common::game::Indexer;
 
-use crate::{game::GameConfig, networking, Users};
+use crate::{
+    game::{DefaultGameConfig, GameConfig},
+    rng::{GameRng, MatchSeed},
+    scores::Scores,
+    Users,
+};
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub enum ServerState {
@@ -42,32 +46,34 @@ pub fn setup_server_offline(mut next: ResMut<NextState<ServerState>>) {
     next.set(ServerState::Running);
 }
 
-pub fn setup_server_online(
-    mut commands: Commands,
-    mut connection: ResMut<Events<ConnectionEvent>>,
-    mut connection_lost: ResMut<Events<ConnectionLostEvent>>,
-    mut server: ResMut<QuinnetServer>,
-) {
+pub fn setup_server_online(mut commands: Commands) {
     commands.init_resource::<Users>();
-    connection.clear();
-    connection_lost.clear();
-    networking::start_server(&mut server);
     info!("Server online!");
 }
 
-pub fn teardown_server_online(mut commands: Commands, mut server: ResMut<QuinnetServer>) {
-    networking::stop_server(&mut server);
+pub fn teardown_server_online(mut commands: Commands) {
     commands.remove_resource::<Users>();
     info!("Server offline!");
 }
 
-pub fn setup_room_running(mut commands: Commands, mut users: ResMut<Users>) {
+pub fn setup_room_running(
+    mut commands: Commands,
+    mut users: ResMut<Users>,
+    config: Res<DefaultGameConfig>,
+    seed: Res<MatchSeed>,
+) {
     users.set_playing();
-    commands.insert_resource(GameConfig::long());
+    commands.insert_resource(config.0.clone());
+    let seed = seed.0.unwrap_or_else(rand::random);
+    info!(seed, "Match started");
+    commands.insert_resource(GameRng::new(seed));
     commands.init_resource::<Indexer>();
+    commands.insert_resource(Scores::default());
 }
 
 pub fn teardown_room_running(mut commands: Commands) {
     commands.remove_resource::<GameConfig>();
     commands.remove_resource::<Indexer>();
+    commands.remove_resource::<GameRng>();
+    commands.remove_resource::<Scores>();
 }
@@ -0,0 +1,70 @@
+//! Drawing themes and constraints.
+
+use std::{convert::Infallible, str::FromStr};
+
+use bevy::prelude::*;
+use common::protocol::HostCommand;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::timer::HostRequest;
+
+/// Default location of the theme list, relative to the working directory.
+pub const THEME_LIST_PATH: &str = "themes.txt";
+
+/// Theme of a drawing round.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum ThemeChoice {
+    /// Freeform drawing.
+    #[default]
+    None,
+    /// Random theme from the theme list.
+    Random,
+    /// Theme set in the game config.
+    Fixed(String),
+}
+
+/// Theme the host set for the next drawing rounds, replacing the one of the schedule.
+#[derive(Resource, Debug, Default)]
+pub struct HostTheme(pub Option<String>);
+
+pub fn set_host_theme(mut requests: EventReader<HostRequest>, mut theme: ResMut<HostTheme>) {
+    for HostRequest(command) in requests.read() {
+        if let HostCommand::SetTheme(choice) = command {
+            theme.0.clone_from(choice);
+        }
+    }
+}
+
+/// Themes to pick from.
+///
+/// The file format is one theme per line, `#` starts a comment.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThemeList {
+    pub themes: Vec<String>,
+}
+
+impl ThemeList {
+    /// Resolve the theme for a round.
+    pub fn pick(&self, choice: &ThemeChoice, rng: &mut impl Rng) -> Option<String> {
+        match choice {
+            ThemeChoice::None => None,
+            ThemeChoice::Random => self.themes.choose(rng).cloned(),
+            ThemeChoice::Fixed(theme) => Some(theme.clone()),
+        }
+    }
+}
+
+impl FromStr for ThemeList {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let themes = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap().trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect();
+        Ok(ThemeList { themes })
+    }
+}
@@ -0,0 +1,191 @@
+//! Timing of the current phase, which the host can pause, extend or skip.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use common::protocol::{Deadline, HostCommand, ServerMsgRoot};
+
+use crate::{states::GameState, transport::Outbox};
+
+/// Host command, already checked to come from the host.
+#[derive(Event, Debug)]
+pub struct HostRequest(pub HostCommand);
+
+/// Deadline of the current phase, shared by every mode.
+#[derive(Resource, Debug, Default)]
+pub struct PhaseTimer {
+    deadline: Deadline,
+    /// Server time the timer was paused at.
+    paused_at: Option<Duration>,
+    /// Whether clients were told the phase is ending.
+    warned: bool,
+}
+
+impl PhaseTimer {
+    /// Start timing a new phase, a pause doesn't carry over.
+    pub fn start(&mut self, now: Duration, duration: Duration) -> Deadline {
+        *self = Self {
+            deadline: Deadline::new(now, duration),
+            ..default()
+        };
+        self.deadline
+    }
+
+    pub fn deadline(&self) -> Deadline {
+        self.deadline
+    }
+
+    pub fn paused_at(&self) -> Option<Duration> {
+        self.paused_at
+    }
+
+    /// Time spent in the phase so far, pauses excluded.
+    pub fn elapsed(&self, now: Duration) -> Duration {
+        let now = self.paused_at.unwrap_or(now);
+        (now + self.deadline.duration).saturating_sub(self.deadline.at)
+    }
+
+    /// Whether the deadline was just reached, clients should be warned once it is.
+    pub fn warn(&mut self, now: Duration) -> bool {
+        let reached = self.paused_at.is_none() && self.deadline.at <= now;
+        if self.warned || !reached {
+            return false;
+        }
+        self.warned = true;
+        true
+    }
+
+    /// Whether the phase is over, once the grace period after the deadline passed.
+    pub fn is_over(&self, now: Duration, grace: Duration) -> bool {
+        self.paused_at.is_none() && self.deadline.at + grace < now
+    }
+
+    pub fn pause(&mut self, now: Duration) {
+        self.paused_at.get_or_insert(now);
+    }
+
+    /// Resume the countdown, pushing the deadline back by the time spent paused.
+    pub fn resume(&mut self, now: Duration) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.deadline.at += now.saturating_sub(paused_at);
+        }
+    }
+
+    pub fn extend(&mut self, by: Duration) {
+        self.deadline.at += by;
+        self.deadline.duration += by;
+        self.warned = false;
+    }
+
+    /// Bring the deadline forward to now, late submissions still get the grace period.
+    pub fn skip(&mut self, now: Duration) {
+        let paused_at = self.paused_at.take();
+        let remaining = self.deadline.at.saturating_sub(paused_at.unwrap_or(now));
+        self.deadline.duration = self.deadline.duration.saturating_sub(remaining);
+        self.deadline.at = match paused_at {
+            Some(_) => now,
+            None => self.deadline.at.min(now),
+        };
+    }
+}
+
+/// Apply host commands to the current phase, broadcasting the new timer.
+pub fn handle_host_commands(
+    mut requests: EventReader<HostRequest>,
+    mut timer: ResMut<PhaseTimer>,
+    mut outbox: EventWriter<Outbox<ServerMsgRoot>>,
+    state: Option<Res<State<GameState>>>,
+    time: Res<Time>,
+) {
+    // Always runs, so requests from before the match don't linger in the reader
+    if state.is_none() {
+        requests.clear();
+        return;
+    }
+    let now = time.elapsed();
+    for HostRequest(command) in requests.read() {
+        info!(?command, "Host command");
+        match *command {
+            HostCommand::Pause => timer.pause(now),
+            HostCommand::Resume => timer.resume(now),
+            HostCommand::Extend(by) => timer.extend(by),
+            HostCommand::Skip => timer.skip(now),
+            // Handled by the prompt phase
+            HostCommand::ApprovePrompt(_) | HostCommand::RejectPrompt(_) => continue,
+            // Handled by the themes
+            HostCommand::SetTheme(_) => continue,
+        }
+        let message = ServerMsgRoot::Timer {
+            deadline: timer.deadline(),
+            paused_at: timer.paused_at(),
+        };
+        outbox.send(Outbox::active(message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn warns_once_at_the_deadline() {
+        let mut timer = PhaseTimer::default();
+        timer.start(secs(10), secs(30));
+        assert!(!timer.warn(secs(39)));
+        assert!(timer.warn(secs(40)));
+        assert!(!timer.warn(secs(41)));
+        assert!(!timer.is_over(secs(42), secs(3)));
+        assert!(timer.is_over(secs(44), secs(3)));
+    }
+
+    #[test]
+    fn pause_pushes_the_deadline_back() {
+        let mut timer = PhaseTimer::default();
+        timer.start(secs(0), secs(30));
+        timer.pause(secs(10));
+        timer.pause(secs(20));
+        assert!(!timer.warn(secs(100)));
+        assert!(!timer.is_over(secs(100), secs(0)));
+        assert_eq!(timer.elapsed(secs(100)), secs(10));
+        timer.resume(secs(60));
+        assert_eq!(timer.elapsed(secs(65)), secs(15));
+        assert_eq!(timer.deadline().at, secs(80));
+        assert_eq!(timer.deadline().duration, secs(30));
+        assert!(timer.paused_at().is_none());
+    }
+
+    #[test]
+    fn extending_warns_again() {
+        let mut timer = PhaseTimer::default();
+        timer.start(secs(0), secs(30));
+        assert!(timer.warn(secs(30)));
+        timer.extend(secs(15));
+        assert_eq!(timer.deadline(), Deadline::new(secs(0), secs(45)));
+        assert!(!timer.warn(secs(31)));
+        assert!(timer.warn(secs(45)));
+    }
+
+    #[test]
+    fn skip_ends_the_phase_after_the_grace_period() {
+        let mut timer = PhaseTimer::default();
+        timer.start(secs(0), secs(30));
+        timer.pause(secs(5));
+        timer.skip(secs(8));
+        assert_eq!(timer.deadline(), Deadline::new(secs(3), secs(5)));
+        assert!(timer.paused_at().is_none());
+        assert!(timer.warn(secs(8)));
+        assert!(!timer.is_over(secs(10), secs(3)));
+        assert!(timer.is_over(secs(12), secs(3)));
+
+        // Skipping during the grace period doesn't extend it
+        timer.start(secs(20), secs(30));
+        assert!(timer.warn(secs(50)));
+        timer.skip(secs(52));
+        assert_eq!(timer.deadline(), Deadline::new(secs(20), secs(30)));
+        assert!(!timer.warn(secs(52)));
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
+use common::protocol::{ClientMsgRoot, NetMsg, ServerMsgRoot};
+
+use super::{Kick, TransportEvent, TransportSet, DEFAULT_CHANNEL};
+use crate::states::ServerState;
+
+/// In-memory transport, with clients driven through the [`Loopback`] resource.
+pub struct LoopbackTransportPlugin;
+
+impl Plugin for LoopbackTransportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Loopback>();
+        app.add_systems(
+            PreUpdate,
+            receive
+                .in_set(TransportSet::Receive)
+                .run_if(in_state(ServerState::Running)),
+        );
+        app.add_systems(
+            PostUpdate,
+            send.in_set(TransportSet::Send)
+                .run_if(in_state(ServerState::Running)),
+        );
+    }
+}
+
+/// Clients connected through memory.
+#[derive(Resource, Debug, Default)]
+pub struct Loopback {
+    last_client: ClientId,
+    events: Vec<TransportEvent>,
+    incoming: Vec<(ClientId, ClientMsgRoot)>,
+    /// Messages received by each connected client.
+    clients: HashMap<ClientId, Vec<ServerMsgRoot>>,
+}
+
+impl Loopback {
+    /// Open a new connection.
+    pub fn connect(&mut self) -> ClientId {
+        self.last_client += 1;
+        let id = self.last_client;
+        self.clients.insert(id, vec![]);
+        self.events.push(TransportEvent::Connected(id));
+        id
+    }
+
+    /// Drop a connection without saying goodbye.
+    pub fn drop_connection(&mut self, client: ClientId) {
+        if self.clients.remove(&client).is_some() {
+            self.events.push(TransportEvent::Lost(client));
+        }
+    }
+
+    /// Send a message from a client.
+    pub fn send(&mut self, client: ClientId, data: ClientMsgRoot) {
+        if self.is_connected(client) {
+            self.incoming.push((client, data));
+        }
+    }
+
+    /// Take all messages a client received so far.
+    pub fn receive(&mut self, client: ClientId) -> Vec<ServerMsgRoot> {
+        self.clients
+            .get_mut(&client)
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    pub fn is_connected(&self, client: ClientId) -> bool {
+        self.clients.contains_key(&client)
+    }
+}
+
+fn receive(
+    mut loopback: ResMut<Loopback>,
+    mut events: EventWriter<TransportEvent>,
+    mut sink: EventWriter<NetMsg<ClientMsgRoot>>,
+) {
+    events.send_batch(loopback.events.drain(..));
+    sink.send_batch(
+        loopback
+            .incoming
+            .drain(..)
+            .map(|(client, data)| NetMsg::new(client, DEFAULT_CHANNEL, data)),
+    );
+}
+
+fn send(
+    mut loopback: ResMut<Loopback>,
+    mut source: EventReader<NetMsg<ServerMsgRoot>>,
+    mut kicks: EventReader<Kick>,
+) {
+    for NetMsg { client, data, .. } in source.read() {
+        if let Some(inbox) = loopback.clients.get_mut(client) {
+            inbox.push(data.clone());
+        }
+    }
+    for Kick(client) in kicks.read() {
+        loopback.clients.remove(client);
+    }
+}
@@ -0,0 +1,118 @@
+//! Seam between the game logic and whatever carries messages to clients.
+//!
+//! Game code sends [`Outbox`] events and reads [`NetMsg<ClientMsgRoot>`] events.
+//! A transport plugin feeds client messages and [`TransportEvent`]s in during
+//! [`TransportSet::Receive`], and delivers [`NetMsg<ServerMsgRoot>`] and [`Kick`]
+//! events during [`TransportSet::Send`]. Several transports can run side by side,
+//! each reading every outgoing event and handling those for its own clients.
+
+pub mod loopback;
+pub mod quinnet;
+pub mod replay;
+pub mod websocket;
+
+use bevy::prelude::*;
+use bevy_quinnet::shared::{channels::ChannelId, ClientId};
+use common::protocol::{ClientMsgRoot, NetMsg, ServerMsgRoot};
+
+use crate::{states::ServerState, users::Users};
+
+/// Channel used for server messages.
+pub const DEFAULT_CHANNEL: ChannelId = 0;
+
+pub struct TransportPlugin;
+
+impl Plugin for TransportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TransportEvent>();
+        app.add_event::<NetMsg<ClientMsgRoot>>();
+        app.add_event::<Outbox<ServerMsgRoot>>();
+        app.add_event::<NetMsg<ServerMsgRoot>>();
+        app.add_event::<Kick>();
+        app.configure_sets(PreUpdate, TransportSet::Receive);
+        app.configure_sets(PostUpdate, TransportSet::Send);
+        app.add_systems(
+            PostUpdate,
+            resolve_outbox::<ServerMsgRoot>
+                .before(TransportSet::Send)
+                .run_if(in_state(ServerState::Running)),
+        );
+    }
+}
+
+#[derive(SystemSet, Hash, PartialEq, Eq, Debug, Clone)]
+pub enum TransportSet {
+    /// Transport reports connections and incoming messages.
+    Receive,
+    /// Transport delivers outgoing messages and kicks.
+    Send,
+}
+
+/// Connection change reported by the transport.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportEvent {
+    Connected(ClientId),
+    Lost(ClientId),
+}
+
+/// Request to close a client's connection.
+#[derive(Event, Debug)]
+pub struct Kick(pub ClientId);
+
+/// Who an outgoing message is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Client(ClientId),
+    /// Every user playing the current game.
+    Active,
+    /// Every user with a name, including those waiting for the next game.
+    Registered,
+}
+
+/// Message waiting to be sent.
+#[derive(Event, Debug)]
+pub struct Outbox<T> {
+    pub target: Target,
+    pub data: T,
+}
+
+impl<T> Outbox<T> {
+    pub fn client(client: ClientId, data: T) -> Self {
+        Self {
+            target: Target::Client(client),
+            data,
+        }
+    }
+
+    pub fn active(data: T) -> Self {
+        Self {
+            target: Target::Active,
+            data,
+        }
+    }
+
+    pub fn registered(data: T) -> Self {
+        Self {
+            target: Target::Registered,
+            data,
+        }
+    }
+}
+
+/// Expand targets into a message per client.
+fn resolve_outbox<T: Clone + Send + Sync + 'static>(
+    mut source: ResMut<Events<Outbox<T>>>,
+    mut sink: EventWriter<NetMsg<T>>,
+    users: Res<Users>,
+) {
+    for Outbox { target, data } in source.drain() {
+        let clients: Vec<ClientId> = match target {
+            Target::Client(client) => vec![client],
+            Target::Active => users.iter_active().map(|(id, _)| *id).collect(),
+            Target::Registered => users.registered.keys().copied().collect(),
+        };
+        for client in clients {
+            sink.send(NetMsg::new(client, DEFAULT_CHANNEL, data.clone()));
+        }
+    }
+}
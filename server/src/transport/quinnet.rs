@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy_quinnet::{
+    server::{
+        certificate::CertificateRetrievalMode, ConnectionEvent, ConnectionLostEvent, QuinnetServer,
+        QuinnetServerPlugin, ServerEndpointConfiguration,
+    },
+    shared::channels::ChannelsConfiguration,
+};
+use common::protocol::{ClientMsgRoot, NetMsg, ServerMsgRoot};
+
+use super::{Kick, TransportEvent, TransportSet};
+use crate::states::ServerState;
+
+/// QUIC transport.
+pub struct QuinnetTransportPlugin;
+
+impl Plugin for QuinnetTransportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(QuinnetServerPlugin::default());
+        app.add_systems(OnEnter(ServerState::Running), start_server);
+        app.add_systems(OnExit(ServerState::Running), stop_server);
+        app.add_systems(
+            PreUpdate,
+            (handle_server_events, receive_messages)
+                .chain()
+                .in_set(TransportSet::Receive)
+                .run_if(in_state(ServerState::Running)),
+        );
+        app.add_systems(
+            PostUpdate,
+            (send_messages, kick_clients)
+                .chain()
+                .in_set(TransportSet::Send)
+                .run_if(in_state(ServerState::Running)),
+        );
+    }
+}
+
+fn start_server(
+    mut connection: ResMut<Events<ConnectionEvent>>,
+    mut connection_lost: ResMut<Events<ConnectionLostEvent>>,
+    mut server: ResMut<QuinnetServer>,
+) {
+    connection.clear();
+    connection_lost.clear();
+    server
+        .start_endpoint(
+            ServerEndpointConfiguration::from_string("0.0.0.0:6000").unwrap(),
+            CertificateRetrievalMode::GenerateSelfSigned {
+                server_hostname: "ApeBox sp. Zloo".to_string(),
+            },
+            ChannelsConfiguration::default(),
+        )
+        .unwrap();
+}
+
+fn stop_server(mut server: ResMut<QuinnetServer>) {
+    server.stop_endpoint().ok();
+}
+
+fn handle_server_events(
+    mut connection: EventReader<ConnectionEvent>,
+    mut connection_lost: EventReader<ConnectionLostEvent>,
+    mut sink: EventWriter<TransportEvent>,
+) {
+    for client in connection.read() {
+        sink.send(TransportEvent::Connected(client.id));
+    }
+    for client in connection_lost.read() {
+        sink.send(TransportEvent::Lost(client.id));
+    }
+}
+
+fn receive_messages(
+    mut server: ResMut<QuinnetServer>,
+    mut sink: EventWriter<NetMsg<ClientMsgRoot>>,
+) {
+    let endpoint = server.endpoint_mut();
+    for client in endpoint.clients() {
+        while let Some((channel, data)) = endpoint.try_receive_message_from::<ClientMsgRoot>(client)
+        {
+            sink.send(NetMsg::new(client, channel, data));
+        }
+    }
+}
+
+fn send_messages(
+    mut source: EventReader<NetMsg<ServerMsgRoot>>,
+    mut server: ResMut<QuinnetServer>,
+) {
+    let endpoint = server.endpoint_mut();
+    for message in source.read() {
+        // Clients of other transports aren't known to the endpoint
+        endpoint
+            .send_message_on(message.client, message.channel, message.data.clone())
+            .ok();
+    }
+}
+
+fn kick_clients(mut source: EventReader<Kick>, mut server: ResMut<QuinnetServer>) {
+    let endpoint = server.endpoint_mut();
+    for Kick(client) in source.read() {
+        endpoint.disconnect_client(*client).ok();
+    }
+}
@@ -0,0 +1,202 @@
+//! Recorded match fed back into the server.
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
+use common::{
+    protocol::{ClientMsgRoot, NetMsg, ServerMsgRoot},
+    replay::{Replay, ReplayEvent},
+};
+
+use super::{TransportEvent, TransportSet, DEFAULT_CHANNEL};
+use crate::{
+    filter::WordFilter,
+    game::{DefaultGameConfig, GameConfig},
+    prompt_bank::PromptBank,
+    rng::MatchSeed,
+    states::{RoomState, ServerState},
+    themes::ThemeList,
+};
+
+/// Plays a replay in place of a network transport, reporting where the server diverges from it.
+///
+/// The game configuration, word filter, prompt bank and themes of the recording replace the
+/// server's own.
+pub struct ReplayTransportPlugin {
+    pub replay: Replay,
+}
+
+impl Plugin for ReplayTransportPlugin {
+    fn build(&self, app: &mut App) {
+        let config: GameConfig = serde_json::from_str(&self.replay.config)
+            .expect("Invalid game configuration in replay");
+        app.insert_resource(DefaultGameConfig(config));
+        let filter: WordFilter =
+            serde_json::from_str(&self.replay.word_filter).expect("Invalid word filter in replay");
+        app.insert_resource(filter);
+        let bank: PromptBank =
+            serde_json::from_str(&self.replay.prompt_bank).expect("Invalid prompt bank in replay");
+        app.insert_resource(bank);
+        let themes: ThemeList =
+            serde_json::from_str(&self.replay.themes).expect("Invalid theme list in replay");
+        app.insert_resource(themes);
+        app.insert_resource(MatchSeed(Some(self.replay.seed)));
+        app.insert_resource(Playback::new(self.replay.clone()));
+        app.add_systems(OnEnter(RoomState::Running), start_playback);
+        app.add_systems(OnExit(RoomState::Running), finish_playback);
+        app.add_systems(
+            PreUpdate,
+            feed_inbound
+                .in_set(TransportSet::Receive)
+                .run_if(in_state(ServerState::Running)),
+        );
+        app.add_systems(PostUpdate, check_outbound.in_set(TransportSet::Send));
+    }
+}
+
+/// Progress through a replay.
+#[derive(Resource, Debug)]
+pub struct Playback {
+    replay: Replay,
+    connected: bool,
+    started: Option<Duration>,
+    /// Next entry to feed.
+    next: usize,
+    /// Recorded messages not yet sent again, per client.
+    expected: HashMap<ClientId, VecDeque<ServerMsgRoot>>,
+    divergences: usize,
+    finished: bool,
+}
+
+impl Playback {
+    fn new(replay: Replay) -> Self {
+        let mut expected = HashMap::<_, VecDeque<_>>::new();
+        for entry in replay.entries.iter() {
+            if let ReplayEvent::Outbound(client, data) = &entry.event {
+                expected.entry(*client).or_default().push_back(data.clone());
+            }
+        }
+        Self {
+            replay,
+            connected: false,
+            started: None,
+            next: 0,
+            expected,
+            divergences: 0,
+            finished: false,
+        }
+    }
+
+    /// Number of messages that differ from the recording.
+    pub fn divergences(&self) -> usize {
+        self.divergences
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+fn start_playback(mut playback: ResMut<Playback>, time: Res<Time>) {
+    if playback.started.is_none() && !playback.finished {
+        info!(seed = playback.replay.seed, "Replay started");
+        playback.started = Some(time.elapsed());
+    }
+}
+
+fn finish_playback(mut playback: ResMut<Playback>, mut exit: EventWriter<AppExit>) {
+    if playback.started.is_none() || playback.finished {
+        return;
+    }
+    let missing = playback.expected.values().map(VecDeque::len).sum::<usize>();
+    playback.divergences += missing;
+    playback.finished = true;
+    if playback.divergences == 0 {
+        info!("Replay finished, matching the recording");
+    } else {
+        warn!(
+            divergences = playback.divergences,
+            "Replay finished, diverging from the recording"
+        );
+    }
+    exit.send(AppExit::Success);
+}
+
+fn feed_inbound(
+    mut playback: ResMut<Playback>,
+    mut transport: EventWriter<TransportEvent>,
+    mut sink: EventWriter<NetMsg<ClientMsgRoot>>,
+    time: Res<Time>,
+) {
+    let playback = &mut *playback;
+    if !playback.connected {
+        playback.connected = true;
+        for (client, name) in playback.replay.players.iter() {
+            transport.send(TransportEvent::Connected(*client));
+            let connect = ClientMsgRoot::Connect { name: name.clone() };
+            sink.send(NetMsg::new(*client, DEFAULT_CHANNEL, connect));
+        }
+    }
+    let (Some(started), false) = (playback.started, playback.finished) else {
+        return;
+    };
+    let now = time.elapsed() - started;
+    while let Some(entry) = playback.replay.entries.get(playback.next) {
+        if now < entry.time {
+            break;
+        }
+        playback.next += 1;
+        match &entry.event {
+            ReplayEvent::Inbound(client, data) => {
+                if matches!(data, ClientMsgRoot::Connect { .. }) {
+                    transport.send(TransportEvent::Connected(*client));
+                }
+                sink.send(NetMsg::new(*client, DEFAULT_CHANNEL, data.clone()));
+            }
+            ReplayEvent::Lost(client) => {
+                transport.send(TransportEvent::Lost(*client));
+            }
+            ReplayEvent::Outbound(..) => {}
+        }
+    }
+}
+
+fn check_outbound(mut playback: ResMut<Playback>, mut source: EventReader<NetMsg<ServerMsgRoot>>) {
+    if playback.started.is_none() || playback.finished {
+        source.clear();
+        return;
+    }
+    for NetMsg { client, data, .. } in source.read() {
+        let expected = playback
+            .expected
+            .get_mut(client)
+            .and_then(VecDeque::pop_front);
+        let matches = expected.is_some_and(|expected| same_message(&expected, data));
+        if !matches {
+            warn!(client, "Message differs from the recording");
+            playback.divergences += 1;
+        }
+    }
+}
+
+/// Messages don't implement `PartialEq`, compare their encoding instead.
+///
+/// Server times depend on frame timing, so they're left out.
+fn same_message(a: &ServerMsgRoot, b: &ServerMsgRoot) -> bool {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    for message in [&mut a, &mut b] {
+        if let Some(deadline) = message.deadline_mut() {
+            deadline.at = Duration::ZERO;
+        }
+        match message {
+            ServerMsgRoot::Pong { server_time, .. } => *server_time = Duration::ZERO,
+            ServerMsgRoot::Timer {
+                paused_at: Some(paused_at),
+                ..
+            } => *paused_at = Duration::ZERO,
+            _ => {}
+        }
+    }
+    bincode::serialize(&a).ok() == bincode::serialize(&b).ok()
+}
@@ -0,0 +1,318 @@
+//! WebSocket transport for browsers and other lightweight clients.
+//!
+//! Every message is a JSON text frame holding a `ClientMsgRoot` or `ServerMsgRoot`.
+
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
+use common::protocol::{ClientMsgRoot, NetMsg, ServerMsgRoot};
+use tungstenite::{Message, WebSocket};
+
+use super::{Kick, TransportEvent, TransportSet, DEFAULT_CHANNEL};
+use crate::states::ServerState;
+
+/// First WebSocket client id, keeping them apart from Quinnet's which count up from 1.
+pub const FIRST_CLIENT_ID: ClientId = 1 << 32;
+
+/// How long socket threads wait before checking for outgoing messages or shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Longest wait before accepting again after repeated errors, like running out of file descriptors.
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+pub struct WebSocketTransportPlugin {
+    pub address: String,
+    /// Most sockets open at once, including those still in the handshake.
+    pub max_connections: usize,
+    /// Longest a client can take to finish the handshake, and to take each outgoing frame.
+    pub handshake_timeout: Duration,
+}
+
+impl Default for WebSocketTransportPlugin {
+    fn default() -> Self {
+        Self {
+            address: "0.0.0.0:6001".to_owned(),
+            max_connections: 256,
+            handshake_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Plugin for WebSocketTransportPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WebSocketConfig {
+            address: self.address.clone(),
+            max_connections: self.max_connections,
+            handshake_timeout: self.handshake_timeout,
+        });
+        app.add_systems(OnEnter(ServerState::Running), start_server);
+        app.add_systems(OnExit(ServerState::Running), stop_server);
+        app.add_systems(
+            PreUpdate,
+            receive_messages
+                .in_set(TransportSet::Receive)
+                .run_if(resource_exists::<WebSocketServer>),
+        );
+        app.add_systems(
+            PostUpdate,
+            (send_messages, kick_clients)
+                .chain()
+                .in_set(TransportSet::Send)
+                .run_if(resource_exists::<WebSocketServer>),
+        );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+struct WebSocketConfig {
+    address: String,
+    max_connections: usize,
+    handshake_timeout: Duration,
+}
+
+/// Running WebSocket endpoint.
+#[derive(Resource, Debug)]
+pub struct WebSocketServer {
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    events: Mutex<Receiver<SocketEvent>>,
+    /// Outgoing frames of each connected client, dropping one closes the socket.
+    clients: HashMap<ClientId, Sender<String>>,
+}
+
+impl WebSocketServer {
+    /// Address the endpoint listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+/// Event from a socket thread.
+#[derive(Debug)]
+enum SocketEvent {
+    Connected(ClientId, Sender<String>),
+    Message(ClientId, ClientMsgRoot),
+    Lost(ClientId),
+}
+
+fn start_server(
+    mut commands: Commands,
+    mut exit: EventWriter<AppExit>,
+    config: Res<WebSocketConfig>,
+) {
+    let listener = match TcpListener::bind(&config.address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(%err, address = config.address, "Failed to bind WebSocket endpoint");
+            exit.send(AppExit::error());
+            return;
+        }
+    };
+    listener.set_nonblocking(true).unwrap();
+    let local_addr = listener.local_addr().unwrap();
+    let running = Arc::new(AtomicBool::new(true));
+    let (events, receiver) = mpsc::channel();
+    {
+        let (running, config) = (running.clone(), config.clone());
+        thread::spawn(move || accept_connections(listener, config, running, events));
+    }
+    info!(%local_addr, "WebSocket endpoint listening");
+    commands.insert_resource(WebSocketServer {
+        local_addr,
+        running,
+        events: Mutex::new(receiver),
+        clients: HashMap::new(),
+    });
+}
+
+fn stop_server(mut commands: Commands, server: Option<Res<WebSocketServer>>) {
+    if let Some(server) = server {
+        server.running.store(false, Ordering::Relaxed);
+    }
+    commands.remove_resource::<WebSocketServer>();
+}
+
+fn receive_messages(
+    mut server: ResMut<WebSocketServer>,
+    mut transport: EventWriter<TransportEvent>,
+    mut sink: EventWriter<NetMsg<ClientMsgRoot>>,
+) {
+    let events = server.events.lock().unwrap().try_iter().collect::<Vec<_>>();
+    for event in events {
+        match event {
+            SocketEvent::Connected(client, outgoing) => {
+                server.clients.insert(client, outgoing);
+                transport.send(TransportEvent::Connected(client));
+            }
+            SocketEvent::Message(client, data) => {
+                if server.clients.contains_key(&client) {
+                    sink.send(NetMsg::new(client, DEFAULT_CHANNEL, data));
+                }
+            }
+            SocketEvent::Lost(client) => {
+                if server.clients.remove(&client).is_some() {
+                    transport.send(TransportEvent::Lost(client));
+                }
+            }
+        }
+    }
+}
+
+fn send_messages(server: Res<WebSocketServer>, mut source: EventReader<NetMsg<ServerMsgRoot>>) {
+    for message in source.read() {
+        let Some(outgoing) = server.clients.get(&message.client) else {
+            continue;
+        };
+        match serde_json::to_string(&message.data) {
+            Ok(text) => {
+                outgoing.send(text).ok();
+            }
+            Err(err) => error!(%err, "Failed to encode message"),
+        }
+    }
+}
+
+fn kick_clients(mut server: ResMut<WebSocketServer>, mut source: EventReader<Kick>) {
+    for Kick(client) in source.read() {
+        server.clients.remove(client);
+    }
+}
+
+/// Held by a socket thread, counting it towards the connection limit.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn accept_connections(
+    listener: TcpListener,
+    config: WebSocketConfig,
+    running: Arc<AtomicBool>,
+    events: Sender<SocketEvent>,
+) {
+    let mut next_client = FIRST_CLIENT_ID;
+    let mut backoff = POLL_INTERVAL;
+    let open = Arc::new(AtomicUsize::new(0));
+    while running.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, address)) => {
+                backoff = POLL_INTERVAL;
+                if open.load(Ordering::Relaxed) >= config.max_connections {
+                    warn!(%address, "Too many WebSocket connections, refusing one");
+                    continue;
+                }
+                open.fetch_add(1, Ordering::Relaxed);
+                let slot = ConnectionSlot(open.clone());
+                let (client, events, running) = (next_client, events.clone(), running.clone());
+                let timeout = config.handshake_timeout;
+                next_client += 1;
+                thread::spawn(move || {
+                    handle_connection(client, stream, timeout, running, events);
+                    drop(slot);
+                });
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                backoff = POLL_INTERVAL;
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(err) => {
+                // Errors like EMFILE persist, retrying right away would spin
+                warn!(%err, retry_in = ?backoff, "Failed to accept WebSocket connection");
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+            }
+        }
+    }
+}
+
+fn handle_connection(
+    client: ClientId,
+    stream: TcpStream,
+    timeout: Duration,
+    running: Arc<AtomicBool>,
+    events: Sender<SocketEvent>,
+) {
+    // Handshake blocks until the timeout, reads time out sooner afterwards so outgoing frames
+    // aren't held up
+    let blocking = stream.set_nonblocking(false).and_then(|_| {
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))
+    });
+    if blocking.is_err() {
+        return;
+    }
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(err) => {
+            warn!(%err, "WebSocket handshake failed");
+            return;
+        }
+    };
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+    let (outgoing, frames) = mpsc::channel();
+    if events
+        .send(SocketEvent::Connected(client, outgoing))
+        .is_err()
+    {
+        return;
+    }
+    run_connection(client, &mut socket, &frames, &running, &events);
+    socket.close(None).ok();
+    socket.flush().ok();
+    events.send(SocketEvent::Lost(client)).ok();
+}
+
+fn run_connection(
+    client: ClientId,
+    socket: &mut WebSocket<TcpStream>,
+    frames: &Receiver<String>,
+    running: &AtomicBool,
+    events: &Sender<SocketEvent>,
+) {
+    while running.load(Ordering::Relaxed) {
+        loop {
+            match frames.try_recv() {
+                Ok(text) => {
+                    if socket.send(Message::Text(text)).is_err() {
+                        return;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                // Kicked
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        match socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(data) => {
+                    events.send(SocketEvent::Message(client, data)).ok();
+                }
+                Err(err) => warn!(client, %err, "Invalid WebSocket message"),
+            },
+            Ok(Message::Close(_)) => return,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => return,
+        }
+    }
+}
@@ -11,6 +11,8 @@ pub struct Users {
     pub pending: HashMap<ClientId, Duration>,
     /// Clients that registered a username.
     pub registered: HashMap<ClientId, UserData>,
+    /// User in control of the match, the first to register.
+    pub host: Option<ClientId>,
 }
 
 impl Users {
@@ -29,6 +31,7 @@ impl Users {
                 playing: false,
             },
         );
+        self.host.get_or_insert(id);
     }
 
     /// Set all registered players to playing.
@@ -38,10 +41,19 @@ impl Users {
         }
     }
 
-    /// Remove all trace of a user.
+    /// Remove all trace of a user, passing the host role on if they had it.
     pub fn remove(&mut self, id: &ClientId) -> Option<UserData> {
         self.pending.remove(id);
-        self.registered.remove(id)
+        let user = self.registered.remove(id);
+        if self.host == Some(*id) {
+            // Lowest id, usually the earliest to join
+            self.host = self.registered.keys().min().copied();
+        }
+        user
+    }
+
+    pub fn is_host(&self, id: ClientId) -> bool {
+        self.host == Some(id)
     }
 
     pub fn iter_active(&self) -> impl Iterator<Item = (&u64, &UserData)> {
@@ -0,0 +1,74 @@
+mod harness;
+
+use std::time::Duration;
+
+use bot::bots::Answers;
+use common::protocol::ServerMsgRoot;
+use harness::Harness;
+use rand::{rngs::StdRng, SeedableRng};
+use server::{
+    game::{LiveTally, StateData},
+    states::{GameState, RoomState},
+};
+
+#[test]
+fn bots_play_a_whole_match() {
+    // Progress and live tallies arrive during every phase the bots answer
+    let mut schedule = harness::schedule();
+    for step in &mut schedule {
+        if let StateData::Vote(vote) = step {
+            vote.live_tally = LiveTally::Counts;
+        }
+    }
+    let mut harness = Harness::new(schedule);
+    let bots = [
+        harness.connect("a"),
+        harness.connect("b"),
+        harness.connect("c"),
+    ];
+    let mut answers = Answers::default();
+    let mut rng = StdRng::seed_from_u64(1);
+    let think_time = Duration::from_secs(2);
+
+    let mut phases = vec![];
+    let mut winners = vec![];
+    for _ in 0..2000 {
+        harness.step();
+        let now = harness.elapsed();
+        for bot in bots {
+            for message in harness.messages(bot) {
+                if let ServerMsgRoot::Winner { winner, loser, .. } = &message {
+                    winners.push(winner.votes + loser.votes);
+                }
+                answers.receive(bot, message, now, think_time, &mut rng);
+            }
+        }
+        for (bot, message) in answers.take_due(now) {
+            harness.submit(bot, message);
+        }
+        let state = harness.game_state();
+        if phases.last().map(|(state, _)| state) != Some(&state) {
+            phases.push((state, now));
+        }
+        // The room waits for the players before it starts
+        if harness.room_state() == Some(RoomState::Waiting) && phases.len() > 1 {
+            break;
+        }
+    }
+
+    // Phases that end once everyone submitted are over well before their deadline
+    for ending in [GameState::Draw, GameState::Prompt, GameState::Combine] {
+        let start = phases
+            .iter()
+            .position(|(state, _)| *state == Some(ending.clone()));
+        let start = start.unwrap_or_else(|| panic!("Match never reached {ending:?}"));
+        let lasted = phases[start + 1].1 - phases[start].1;
+        assert!(
+            lasted < Duration::from_secs(10),
+            "{ending:?} lasted {lasted:?}"
+        );
+    }
+    assert!(!winners.is_empty());
+    assert!(winners.iter().all(|votes| *votes > 0), "{winners:?}");
+    assert_eq!(harness.room_state(), Some(RoomState::Waiting));
+}
@@ -0,0 +1,247 @@
+//! Server app driven through the loopback transport.
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_quinnet::shared::ClientId;
+use common::{
+    game::{Combination, CustomFont, Drawing, Picture, Prompt, Vote, IMG_SIZE},
+    protocol::{ClientMsgComm, ClientMsgRoot, ServerMsgRoot},
+    replay::Replay,
+};
+use server::{
+    game::{
+        CombineConfig, DefaultGameConfig, DrawConfig, GameConfig, LiveTally, PromptConfig,
+        RevealConfig, StateData, VoteConfig,
+    },
+    states::{GameState, RoomState},
+    themes::ThemeChoice,
+    transport::{
+        loopback::{Loopback, LoopbackTransportPlugin},
+        replay::ReplayTransportPlugin,
+    },
+    ServerPlugin,
+};
+
+/// Time advanced by a single step.
+pub const STEP: Duration = Duration::from_millis(100);
+
+pub struct Harness {
+    pub app: App,
+}
+
+impl Harness {
+    /// Server with a schedule of steps, in the order they're played.
+    pub fn new(steps: Vec<StateData>) -> Self {
+        Self::with_transport(LoopbackTransportPlugin, |app| {
+            app.insert_resource(DefaultGameConfig(GameConfig {
+                extra_time: Duration::from_secs(1),
+                states: steps.into_iter().rev().collect(),
+            }));
+        })
+    }
+
+    /// Server playing a replay.
+    pub fn playback(replay: Replay) -> Self {
+        Self::with_transport(ReplayTransportPlugin { replay }, |_| {})
+    }
+
+    fn with_transport(transport: impl Plugin, setup: impl FnOnce(&mut App)) -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ServerPlugin, transport));
+        setup(&mut app);
+        app.world_mut()
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(Duration::from_secs(3600));
+        let mut harness = Self { app };
+        harness.settle();
+        harness
+    }
+
+    /// Connect and register a fake client.
+    pub fn connect(&mut self, name: &str) -> ClientId {
+        let id = self.loopback().connect();
+        self.send(
+            id,
+            ClientMsgRoot::Connect {
+                name: name.to_owned(),
+            },
+        );
+        id
+    }
+
+    pub fn send(&mut self, client: ClientId, data: ClientMsgRoot) {
+        self.loopback().send(client, data);
+    }
+
+    pub fn submit(&mut self, client: ClientId, data: ClientMsgComm) {
+        self.send(client, data.root());
+    }
+
+    /// Run one frame after advancing time.
+    pub fn advance(&mut self, by: Duration) {
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(by));
+        self.app.update();
+    }
+
+    pub fn step(&mut self) {
+        self.advance(STEP);
+    }
+
+    /// Run enough frames for submissions to be processed and state changes to apply.
+    pub fn settle(&mut self) {
+        for _ in 0..3 {
+            self.step();
+        }
+    }
+
+    /// Run frames until the given time passes.
+    pub fn run_for(&mut self, duration: Duration) {
+        let mut elapsed = Duration::ZERO;
+        while elapsed < duration {
+            self.step();
+            elapsed += STEP;
+        }
+    }
+
+    /// Step until the room stops, with players answering every message.
+    ///
+    /// Returns every message each player received.
+    pub fn play_match(
+        &mut self,
+        players: &[ClientId],
+        answer: impl Fn(usize, &ServerMsgRoot) -> Vec<ClientMsgComm>,
+    ) -> Vec<Vec<ServerMsgRoot>> {
+        let mut received = vec![vec![]; players.len()];
+        for _ in 0..1000 {
+            self.step();
+            for (i, player) in players.iter().enumerate() {
+                for message in self.messages(*player) {
+                    for answer in answer(i, &message) {
+                        self.submit(*player, answer);
+                    }
+                    received[i].push(message);
+                }
+            }
+            if self.room_state() == Some(RoomState::Waiting) {
+                break;
+            }
+        }
+        received
+    }
+
+    /// Take all messages sent to a client so far.
+    pub fn messages(&mut self, client: ClientId) -> Vec<ServerMsgRoot> {
+        self.loopback().receive(client)
+    }
+
+    /// Take the latest message sent to a client, skipping the progress sent along with phases.
+    pub fn last_message(&mut self, client: ClientId) -> Option<ServerMsgRoot> {
+        let mut messages = self.messages(client).into_iter();
+        messages.rfind(|m| !matches!(m, ServerMsgRoot::Progress(_)))
+    }
+
+    pub fn drop_connection(&mut self, client: ClientId) {
+        self.loopback().drop_connection(client);
+    }
+
+    pub fn is_connected(&self, client: ClientId) -> bool {
+        self.app.world().resource::<Loopback>().is_connected(client)
+    }
+
+    fn loopback(&mut self) -> Mut<'_, Loopback> {
+        self.app.world_mut().resource_mut::<Loopback>()
+    }
+
+    pub fn room_state(&self) -> Option<RoomState> {
+        let state = self.app.world().get_resource::<State<RoomState>>();
+        state.map(|s| s.get().clone())
+    }
+
+    pub fn game_state(&self) -> Option<GameState> {
+        let state = self.app.world().get_resource::<State<GameState>>();
+        state.map(|s| s.get().clone())
+    }
+
+    /// Server time.
+    pub fn elapsed(&self) -> Duration {
+        self.app.world().resource::<Time>().elapsed()
+    }
+
+    /// Number of entities with a component.
+    pub fn count<T: Component>(&mut self) -> usize {
+        let world = self.app.world_mut();
+        world.query::<&T>().iter(world).count()
+    }
+}
+
+/// Match going through every phase once, with several submissions per player.
+pub fn schedule() -> Vec<StateData> {
+    vec![
+        StateData::Draw(DrawConfig {
+            drawings_per_player: 2,
+            duration: Duration::from_secs(60),
+            theme: ThemeChoice::None,
+        }),
+        StateData::Prompt(PromptConfig {
+            prompts_per_player: 2,
+            duration: Duration::from_secs(30),
+            house_ratio: 0.0,
+            house_category: None,
+        }),
+        StateData::Combine(CombineConfig {
+            duration: Duration::from_secs(30),
+        }),
+        StateData::Vote(VoteConfig {
+            voting_duration: Duration::from_secs(10),
+            winner_duration: Duration::from_secs(5),
+            reveal: reveal(),
+            anonymous: false,
+            live_tally: LiveTally::Off,
+        }),
+    ]
+}
+
+/// Reveal sequence fitting in the winner screen of [`schedule`].
+pub fn reveal() -> RevealConfig {
+    RevealConfig {
+        votes: Duration::from_secs(1),
+        authors: Duration::from_secs(2),
+        score: Duration::from_secs(3),
+    }
+}
+
+/// Answer a phase like a player would, with content unique to the player.
+pub fn answer(player: usize, message: &ServerMsgRoot) -> Vec<ClientMsgComm> {
+    let player = player as u8;
+    match message {
+        ServerMsgRoot::Draw { drawings, .. } => (0..*drawings as u8)
+            .map(|i| {
+                ClientMsgComm::SubmitDrawing(Drawing {
+                    drawing: Picture::Pixels(vec![player * 10 + i; IMG_SIZE * IMG_SIZE * 4]),
+                    bg_color: [0; 3],
+                })
+            })
+            .collect(),
+        ServerMsgRoot::Prompt { prompts, .. } => (0..*prompts)
+            .map(|i| {
+                ClientMsgComm::SubmitPrompt(Prompt {
+                    text: format!("Prompt {player}-{i}"),
+                    font: CustomFont(0),
+                })
+            })
+            .collect(),
+        ServerMsgRoot::Combine {
+            drawings, prompts, ..
+        } => vec![ClientMsgComm::SubmitCombination(Combination {
+            drawing: drawings[0].0,
+            prompt: prompts[0].0,
+        })],
+        ServerMsgRoot::Vote { combination1, .. } => vec![ClientMsgComm::SubmitVote(Vote {
+            combination: combination1.0,
+        })],
+        _ => vec![],
+    }
+}
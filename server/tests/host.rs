@@ -0,0 +1,251 @@
+mod harness;
+
+use std::time::Duration;
+
+use bevy_quinnet::shared::ClientId;
+use common::{
+    game::{CustomFont, Drawing, Index, Picture, Prompt, IMG_SIZE},
+    protocol::{ClientMsgComm, ClientMsgRoot, HostCommand, ServerMsgRoot},
+};
+use harness::Harness;
+use server::{
+    filter::{Flagged, WordFilter},
+    game::{DrawConfig, PromptConfig, StateData},
+    states::GameState,
+    themes::ThemeChoice,
+    users::Users,
+};
+
+/// Room in the draw phase, which lasts 60 seconds, with the host first.
+fn start() -> (Harness, ClientId, ClientId) {
+    let mut harness = Harness::new(harness::schedule());
+    let host = harness.connect("host");
+    let player = harness.connect("player");
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    (harness, host, player)
+}
+
+fn command(harness: &mut Harness, client: ClientId, command: HostCommand) {
+    harness.send(client, ClientMsgRoot::Host(command));
+    harness.step();
+}
+
+/// Whether the messages make the client host.
+fn is_host(messages: Vec<ServerMsgRoot>) -> bool {
+    messages.iter().any(|m| matches!(m, ServerMsgRoot::Host))
+}
+
+#[test]
+fn first_player_is_host() {
+    let (mut harness, host, player) = start();
+    assert!(is_host(harness.messages(host)));
+    assert!(!is_host(harness.messages(player)));
+
+    // Role passes on when the host leaves
+    harness.send(host, ClientMsgRoot::Disconnect);
+    harness.settle();
+    assert!(is_host(harness.messages(player)));
+    assert_eq!(harness.app.world().resource::<Users>().host, Some(player));
+}
+
+#[test]
+fn pause_freezes_the_phase() {
+    let (mut harness, host, player) = start();
+    command(&mut harness, host, HostCommand::Pause);
+    let Some(ServerMsgRoot::Timer {
+        paused_at: Some(_), ..
+    }) = harness.last_message(player)
+    else {
+        panic!("Expected paused timer");
+    };
+    harness.run_for(Duration::from_secs(120));
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+
+    command(&mut harness, host, HostCommand::Resume);
+    let Some(ServerMsgRoot::Timer {
+        deadline,
+        paused_at: None,
+    }) = harness.last_message(player)
+    else {
+        panic!("Expected running timer");
+    };
+    assert!(deadline.at > harness.elapsed() + Duration::from_secs(59));
+    harness.run_for(Duration::from_secs(59));
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    // Extra time passes too
+    harness.run_for(Duration::from_secs(3));
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+}
+
+#[test]
+fn extend_pushes_the_deadline_back() {
+    let (mut harness, host, player) = start();
+    command(
+        &mut harness,
+        host,
+        HostCommand::Extend(Duration::from_secs(30)),
+    );
+    let Some(ServerMsgRoot::Timer { deadline, .. }) = harness.last_message(player) else {
+        panic!("Expected timer");
+    };
+    assert_eq!(deadline.duration, Duration::from_secs(90));
+    harness.run_for(Duration::from_secs(80));
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    harness.run_for(Duration::from_secs(12));
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+}
+
+#[test]
+fn skip_ends_the_phase_after_the_grace_period() {
+    let (mut harness, host, player) = start();
+    harness.messages(player);
+    command(&mut harness, host, HostCommand::Skip);
+    harness.step();
+    let messages = harness.messages(player);
+    assert!(messages
+        .iter()
+        .any(|m| matches!(m, ServerMsgRoot::PhaseEnding)));
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+
+    // Work in progress sent on the warning still counts
+    for _ in 0..2 {
+        harness.submit(player, drawing());
+    }
+    harness.settle();
+    assert_eq!(harness.count::<Drawing>(), 2);
+    harness.run_for(Duration::from_secs(1));
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+}
+
+#[test]
+fn players_cannot_use_host_commands() {
+    let (mut harness, _host, player) = start();
+    command(&mut harness, player, HostCommand::Skip);
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    assert!(harness.is_connected(player));
+}
+
+fn drawing() -> ClientMsgComm {
+    ClientMsgComm::SubmitDrawing(Drawing {
+        drawing: Picture::Pixels(vec![0; IMG_SIZE * IMG_SIZE * 4]),
+        bg_color: [255; 3],
+    })
+}
+
+fn submit_prompt(harness: &mut Harness, client: ClientId, text: &str) {
+    let prompt = Prompt {
+        text: text.to_owned(),
+        font: CustomFont(0),
+    };
+    harness.submit(client, ClientMsgComm::SubmitPrompt(prompt));
+    harness.step();
+}
+
+/// Index of the prompt the host was asked to review.
+fn review(harness: &mut Harness, host: ClientId) -> Index {
+    let messages = harness.messages(host);
+    let review = messages.iter().find_map(|m| match m {
+        ServerMsgRoot::PromptReview { prompt, .. } => Some(prompt.0),
+        _ => None,
+    });
+    review.expect("Expected a prompt to review")
+}
+
+#[test]
+fn host_reviews_held_back_prompts() {
+    let prompt = StateData::Prompt(PromptConfig {
+        prompts_per_player: 1,
+        duration: Duration::from_secs(30),
+        house_ratio: 0.0,
+        house_category: None,
+    });
+    let mut harness = Harness::new(vec![prompt.clone(), prompt]);
+    let filter = "@action review\nape".parse::<WordFilter>().unwrap();
+    harness.app.insert_resource(filter);
+    let host = harness.connect("host");
+    let player = harness.connect("player");
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+    harness.messages(player);
+
+    // Rejected prompts go back to their author, who can write another one
+    submit_prompt(&mut harness, player, "an ape");
+    assert_eq!(harness.count::<Flagged>(), 1);
+    let index = review(&mut harness, host);
+    command(&mut harness, host, HostCommand::RejectPrompt(index));
+    assert_eq!(harness.count::<Flagged>(), 0);
+    assert!(matches!(
+        harness.last_message(player),
+        Some(ServerMsgRoot::PromptRejected(prompt)) if prompt.text == "an ape"
+    ));
+
+    // Held back prompts only count once approved
+    submit_prompt(&mut harness, player, "another ape");
+    submit_prompt(&mut harness, host, "a monkey");
+    harness.settle();
+    let started_next = |messages: Vec<ServerMsgRoot>| {
+        messages
+            .iter()
+            .any(|m| matches!(m, ServerMsgRoot::Prompt { .. }))
+    };
+    assert!(!started_next(harness.messages(player)));
+    let index = review(&mut harness, host);
+    command(&mut harness, host, HostCommand::ApprovePrompt(index));
+    harness.settle();
+    // Everyone submitted, so the next prompt phase started
+    assert!(started_next(harness.messages(player)));
+    assert_eq!(harness.count::<Prompt>(), 2);
+}
+
+#[test]
+fn unreviewed_prompts_are_let_in_at_the_deadline() {
+    let prompt = StateData::Prompt(PromptConfig {
+        prompts_per_player: 1,
+        duration: Duration::from_secs(30),
+        house_ratio: 0.0,
+        house_category: None,
+    });
+    let mut harness = Harness::new(vec![prompt.clone(), prompt]);
+    let filter = "@action review\nape".parse::<WordFilter>().unwrap();
+    harness.app.insert_resource(filter);
+    let host = harness.connect("host");
+    let player = harness.connect("player");
+    harness.settle();
+
+    submit_prompt(&mut harness, player, "an ape");
+    submit_prompt(&mut harness, host, "a monkey");
+    harness.settle();
+    assert_eq!(harness.count::<Flagged>(), 1);
+    harness.run_for(Duration::from_secs(32));
+    assert_eq!(harness.count::<Flagged>(), 0);
+    assert_eq!(harness.count::<Prompt>(), 2);
+}
+
+#[test]
+fn host_sets_the_theme_of_next_drawing_rounds() {
+    let draw = || {
+        StateData::Draw(DrawConfig {
+            drawings_per_player: 1,
+            duration: Duration::from_secs(60),
+            theme: ThemeChoice::None,
+        })
+    };
+    let mut harness = Harness::new(vec![draw(), draw()]);
+    let host = harness.connect("host");
+    let player = harness.connect("player");
+    harness.settle();
+
+    // Only the host's theme counts
+    let theme = |theme: &str| HostCommand::SetTheme(Some(theme.to_owned()));
+    command(&mut harness, player, theme("Pirates"));
+    command(&mut harness, host, theme("Dinosaurs"));
+    harness.submit(host, drawing());
+    harness.submit(player, drawing());
+    harness.settle();
+    let Some(ServerMsgRoot::Draw { theme, .. }) = harness.last_message(player) else {
+        panic!("Expected draw message");
+    };
+    assert_eq!(theme.as_deref(), Some("Dinosaurs"));
+}
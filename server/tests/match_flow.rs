@@ -0,0 +1,667 @@
+mod harness;
+
+use std::time::Duration;
+
+use bevy_quinnet::shared::ClientId;
+
+use common::{
+    game::{Combination, CustomFont, Drawing, Picture, Prompt, RevealStage, Vote, IMG_SIZE},
+    protocol::{ClientMsgComm, ClientMsgRoot, ServerMsgRoot},
+    strokes::{self, VectorEdit, VectorStroke, MAX_STROKES},
+};
+use harness::Harness;
+use server::{
+    filter::WordFilter,
+    game::{CombineConfig, DrawConfig, LiveTally, PromptConfig, StateData, VoteConfig},
+    scores::Scores,
+    states::{GameState, RoomState},
+    themes::ThemeChoice,
+};
+
+fn draw(duration: u64) -> StateData {
+    StateData::Draw(DrawConfig {
+        drawings_per_player: 1,
+        duration: Duration::from_secs(duration),
+        theme: ThemeChoice::None,
+    })
+}
+
+fn prompt(prompts_per_player: usize) -> StateData {
+    StateData::Prompt(PromptConfig {
+        prompts_per_player,
+        duration: Duration::from_secs(30),
+        house_ratio: 0.0,
+        house_category: None,
+    })
+}
+
+fn combine() -> StateData {
+    StateData::Combine(CombineConfig {
+        duration: Duration::from_secs(30),
+    })
+}
+
+fn vote() -> StateData {
+    StateData::Vote(VoteConfig {
+        voting_duration: Duration::from_secs(10),
+        winner_duration: Duration::from_secs(5),
+        reveal: harness::reveal(),
+        anonymous: false,
+        live_tally: LiveTally::Off,
+    })
+}
+
+fn drawing() -> ClientMsgComm {
+    ClientMsgComm::SubmitDrawing(Drawing {
+        drawing: Picture::Pixels(vec![0; IMG_SIZE * IMG_SIZE * 4]),
+        bg_color: [255; 3],
+    })
+}
+
+fn prompt_text(text: &str) -> ClientMsgComm {
+    ClientMsgComm::SubmitPrompt(Prompt {
+        text: text.to_owned(),
+        font: CustomFont(0),
+    })
+}
+
+#[test]
+fn full_match() {
+    let mut harness = Harness::new(vec![draw(60), prompt(1), combine(), vote()]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    assert_eq!(harness.room_state(), Some(RoomState::Running));
+    let messages = harness.messages(b);
+    assert!(matches!(messages[0], ServerMsgRoot::Wait));
+    assert!(matches!(
+        messages[1],
+        ServerMsgRoot::Draw { drawings: 1, .. }
+    ));
+
+    harness.submit(a, drawing());
+    harness.submit(b, drawing());
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+    assert!(matches!(
+        harness.last_message(b),
+        Some(ServerMsgRoot::Prompt { prompts: 1, .. })
+    ));
+
+    harness.submit(a, prompt_text("Monkey business"));
+    harness.submit(b, prompt_text("Banana split"));
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Combine));
+    for client in [a, b] {
+        let Some(ServerMsgRoot::Combine {
+            drawings, prompts, ..
+        }) = harness.last_message(client)
+        else {
+            panic!("Expected combine message");
+        };
+        assert_eq!((drawings.len(), prompts.len()), (1, 1));
+        harness.submit(
+            client,
+            ClientMsgComm::SubmitCombination(Combination {
+                drawing: drawings[0].0,
+                prompt: prompts[0].0,
+            }),
+        );
+    }
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Vote));
+
+    let Some(ServerMsgRoot::Vote {
+        combination1,
+        combination2,
+        ..
+    }) = harness.last_message(a)
+    else {
+        panic!("Expected vote message");
+    };
+    let vote = |combination| ClientMsgComm::SubmitVote(Vote { combination });
+    harness.submit(a, vote(combination2.0));
+    harness.submit(b, vote(combination1.0));
+    harness.settle();
+    // Votes can change until the deadline
+    assert_eq!(harness.game_state(), Some(GameState::Vote));
+    harness.submit(a, vote(combination1.0));
+    harness.run_for(Duration::from_secs(11));
+    let Some(ServerMsgRoot::Winner { winner, loser, .. }) = harness.last_message(b) else {
+        panic!("Expected winner message");
+    };
+    assert_eq!(winner.prompt.text, combination1.2.text);
+    assert_eq!((winner.votes, loser.votes), (2, 0));
+    assert_eq!(winner.voters, ["a", "b"]);
+    assert!(loser.voters.is_empty());
+
+    // Schedule ends, the room restarts with everyone still connected
+    harness.run_for(Duration::from_secs(11));
+    assert_eq!(harness.room_state(), Some(RoomState::Running));
+    assert!(matches!(
+        harness.last_message(a),
+        Some(ServerMsgRoot::Draw { .. })
+    ));
+}
+
+#[test]
+fn winner_screen_reveals_the_result() {
+    let mut harness = Harness::new(vec![draw(60), prompt(1), combine(), vote()]);
+    let players = [harness.connect("a"), harness.connect("b")];
+    let winner = play_until_winner(&mut harness, &players);
+    let ServerMsgRoot::Winner {
+        deadline,
+        winner,
+        points,
+        ..
+    } = winner
+    else {
+        unreachable!();
+    };
+    // Both players vote for the first combination
+    assert_eq!(points, 200);
+    let combiner = if winner.credits.combiner == "a" { 0 } else { 1 };
+    let scores = harness.app.world().resource::<Scores>();
+    assert_eq!(scores.get(players[combiner]), 200);
+
+    // Parts of the result come one after the other, at the configured times
+    let started = deadline.at - deadline.duration;
+    let mut reveals = vec![];
+    while harness.elapsed() < deadline.at - harness::STEP {
+        harness.step();
+        for message in harness.messages(players[0]) {
+            if let ServerMsgRoot::Reveal(stage) = message {
+                reveals.push((stage, harness.elapsed() - started));
+            }
+        }
+    }
+    let stages: Vec<_> = reveals.iter().map(|(stage, _)| *stage).collect();
+    assert_eq!(
+        stages,
+        [RevealStage::Votes, RevealStage::Authors, RevealStage::Score]
+    );
+    let config = harness::reveal();
+    for (stage, at) in reveals {
+        assert!(at >= config.at(stage) && at < config.at(stage) + harness::STEP * 2);
+    }
+
+    // The screen lasts the winner duration, not the voting one
+    assert_eq!(deadline.duration, Duration::from_secs(5));
+    assert_eq!(harness.game_state(), Some(GameState::Vote));
+    harness.settle();
+    assert_ne!(harness.game_state(), Some(GameState::Vote));
+}
+
+#[test]
+fn anonymous_votes_only_send_counts() {
+    let StateData::Vote(config) = vote() else {
+        unreachable!();
+    };
+    let anonymous = StateData::Vote(VoteConfig {
+        anonymous: true,
+        ..config
+    });
+    let mut harness = Harness::new(vec![draw(60), prompt(1), combine(), anonymous]);
+    let players = [harness.connect("a"), harness.connect("b")];
+    let ServerMsgRoot::Winner { winner, .. } = play_until_winner(&mut harness, &players) else {
+        unreachable!();
+    };
+    assert_eq!(winner.votes, 2);
+    assert!(winner.voters.is_empty());
+}
+
+#[test]
+fn live_tally_is_sent_as_votes_arrive() {
+    let StateData::Vote(config) = vote() else {
+        unreachable!();
+    };
+    let live = StateData::Vote(VoteConfig {
+        live_tally: LiveTally::Counts,
+        ..config
+    });
+    let mut harness = Harness::new(vec![draw(60), prompt(1), combine(), live]);
+    let players = [harness.connect("a"), harness.connect("b")];
+    let ServerMsgRoot::Vote { combination2, .. } = play_until(&mut harness, &players, |m| {
+        matches!(m, ServerMsgRoot::Vote { .. })
+    }) else {
+        unreachable!();
+    };
+    harness.step();
+    let tally = |harness: &mut Harness| {
+        let messages = harness.messages(players[1]);
+        let mut tallies = messages.into_iter().filter_map(|m| match m {
+            ServerMsgRoot::Tally(tally) => Some(tally),
+            _ => None,
+        });
+        tallies.next_back()
+    };
+    let empty = tally(&mut harness).unwrap();
+    assert_eq!(
+        (empty.voted, empty.players, empty.votes),
+        (0, 2, Some((0, 0)))
+    );
+
+    harness.submit(
+        players[0],
+        ClientMsgComm::SubmitVote(Vote {
+            combination: combination2.0,
+        }),
+    );
+    harness.step();
+    let tally1 = tally(&mut harness).unwrap();
+    assert_eq!((tally1.voted, tally1.votes), (1, Some((0, 1))));
+
+    // Nothing changed, nothing sent
+    harness.step();
+    assert!(tally(&mut harness).is_none());
+}
+
+/// Answer every phase until the first vote result, which is returned.
+fn play_until_winner(harness: &mut Harness, players: &[ClientId]) -> ServerMsgRoot {
+    play_until(harness, players, |m| {
+        matches!(m, ServerMsgRoot::Winner { .. })
+    })
+}
+
+/// Answer every phase until a message matches, which is returned without being answered.
+fn play_until(
+    harness: &mut Harness,
+    players: &[ClientId],
+    until: impl Fn(&ServerMsgRoot) -> bool,
+) -> ServerMsgRoot {
+    loop {
+        harness.step();
+        for (i, player) in players.iter().enumerate() {
+            for message in harness.messages(*player) {
+                if until(&message) {
+                    return message;
+                }
+                for answer in harness::answer(i, &message) {
+                    harness.submit(*player, answer);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn progress_lists_who_is_done() {
+    // Two drawings per player
+    let mut harness = Harness::new(harness::schedule());
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    let progress = |messages: Vec<ServerMsgRoot>| {
+        let mut progress = messages.into_iter().filter_map(|m| match m {
+            ServerMsgRoot::Progress(progress) => Some(progress),
+            _ => None,
+        });
+        progress.next_back()
+    };
+    let start = progress(harness.messages(b)).unwrap();
+    assert!(start.done.is_empty());
+    assert_eq!(start.waiting, ["a", "b"]);
+
+    // Only a full quota counts
+    harness.submit(a, drawing());
+    harness.step();
+    assert!(progress(harness.messages(b)).is_none());
+    harness.submit(a, drawing());
+    harness.step();
+    let done = progress(harness.messages(b)).unwrap();
+    assert_eq!(
+        (done.done, done.waiting),
+        (vec!["a".to_owned()], vec!["b".to_owned()])
+    );
+}
+
+#[test]
+fn phase_times_out() {
+    let mut harness = Harness::new(vec![draw(30), prompt(1)]);
+    let a = harness.connect("a");
+    let _b = harness.connect("b");
+    harness.settle();
+
+    harness.submit(a, drawing());
+    harness.run_for(Duration::from_secs(30));
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+
+    // Extra time passes too
+    harness.run_for(Duration::from_secs(2));
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+}
+
+#[test]
+fn idle_player_sits_out_combine() {
+    let mut harness = Harness::new(vec![draw(5), prompt(1), combine(), vote()]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    let c = harness.connect("c");
+    harness.settle();
+
+    // Only two drawings for three players
+    harness.submit(a, drawing());
+    harness.submit(b, drawing());
+    harness.run_for(Duration::from_secs(7));
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+    for (client, text) in [(a, "Monkey"), (b, "Banana"), (c, "Jungle")] {
+        harness.submit(client, prompt_text(text));
+    }
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Combine));
+
+    // Players are shuffled, so any of them can be left out
+    let mut idle = 0;
+    for client in [a, b, c] {
+        let Some(ServerMsgRoot::Combine {
+            drawings, prompts, ..
+        }) = harness.last_message(client)
+        else {
+            panic!("Expected combine message");
+        };
+        let (Some(drawing), Some(prompt)) = (drawings.first(), prompts.first()) else {
+            idle += 1;
+            continue;
+        };
+        harness.submit(
+            client,
+            ClientMsgComm::SubmitCombination(Combination {
+                drawing: drawing.0,
+                prompt: prompt.0,
+            }),
+        );
+    }
+    assert_eq!(idle, 1);
+    // The idle player doesn't hold the phase up
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Vote));
+}
+
+#[test]
+fn vote_is_skipped_without_two_combinations() {
+    let mut harness = Harness::new(vec![draw(5), prompt(1), combine(), vote()]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+
+    harness.submit(a, drawing());
+    harness.run_for(Duration::from_secs(7));
+    harness.submit(a, prompt_text("Monkey"));
+    harness.submit(b, prompt_text("Banana"));
+    harness.settle();
+    // Players are shuffled, so either of them gets the only drawing
+    for client in [a, b] {
+        let Some(ServerMsgRoot::Combine {
+            drawings, prompts, ..
+        }) = harness.last_message(client)
+        else {
+            panic!("Expected combine message");
+        };
+        if let (Some(drawing), Some(prompt)) = (drawings.first(), prompts.first()) {
+            harness.submit(
+                client,
+                ClientMsgComm::SubmitCombination(Combination {
+                    drawing: drawing.0,
+                    prompt: prompt.0,
+                }),
+            );
+        }
+    }
+    harness.settle();
+    assert_eq!(harness.room_state(), Some(RoomState::Waiting));
+}
+
+#[test]
+fn deadline_is_in_server_time() {
+    let mut harness = Harness::new(vec![draw(30), prompt(1)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    let Some(ServerMsgRoot::Draw { deadline, .. }) = harness.last_message(a) else {
+        panic!("Expected draw message");
+    };
+    assert_eq!(deadline.duration, Duration::from_secs(30));
+    let remaining = deadline.at - harness.elapsed();
+    assert!(remaining <= deadline.duration);
+    assert!(remaining > deadline.duration - Duration::from_secs(1));
+
+    // Work submitted as the timer runs out still counts, within the extra time
+    harness.submit(a, drawing());
+    harness.run_for(deadline.at - harness.elapsed() + Duration::from_millis(500));
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    harness.submit(b, drawing());
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+    assert_eq!(harness.count::<Drawing>(), 2);
+}
+
+#[test]
+fn players_are_warned_once_when_the_phase_ends() {
+    let mut harness = Harness::new(vec![draw(30), prompt(1)]);
+    let a = harness.connect("a");
+    let _b = harness.connect("b");
+    harness.settle();
+    harness.messages(a);
+
+    harness.run_for(Duration::from_secs(29));
+    assert!(harness.messages(a).is_empty());
+    harness.run_for(Duration::from_secs(1));
+    let warnings = |messages: Vec<ServerMsgRoot>| {
+        messages
+            .iter()
+            .filter(|m| matches!(m, ServerMsgRoot::PhaseEnding))
+            .count()
+    };
+    assert_eq!(warnings(harness.messages(a)), 1);
+
+    // Still in the grace period, without more warnings
+    harness.step();
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    assert_eq!(warnings(harness.messages(a)), 0);
+}
+
+#[test]
+fn ping_is_answered_with_server_time() {
+    let mut harness = Harness::new(vec![draw(30)]);
+    let a = harness.connect("a");
+    harness.settle();
+    harness.messages(a);
+
+    let sent = Duration::from_secs(1234);
+    harness.send(a, ClientMsgRoot::Ping { sent });
+    harness.step();
+    let now = harness.elapsed();
+    let Some(ServerMsgRoot::Pong {
+        sent: echoed,
+        server_time,
+    }) = harness.last_message(a)
+    else {
+        panic!("Expected pong");
+    };
+    assert_eq!(echoed, sent);
+    assert!(server_time <= now);
+    assert!(server_time + Duration::from_secs(1) > now);
+}
+
+#[test]
+fn phase_completes_early_once_every_quota_is_met() {
+    let mut harness = Harness::new(vec![prompt(2), draw(30)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+
+    // One player can't fill the phase alone
+    for text in ["One", "Two", "Three", "Four"] {
+        harness.submit(a, prompt_text(text));
+    }
+    harness.submit(b, prompt_text("Five"));
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+    assert_eq!(harness.count::<Prompt>(), 3);
+
+    harness.submit(b, prompt_text("Six"));
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    assert_eq!(harness.count::<Prompt>(), 4);
+}
+
+#[test]
+fn filtered_prompts_are_sent_back() {
+    let mut harness = Harness::new(vec![prompt(1), draw(30)]);
+    let filter = "ape".parse::<WordFilter>().unwrap();
+    harness.app.insert_resource(filter);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    harness.submit(b, prompt_text("A horse"));
+    harness.settle();
+    harness.messages(a);
+
+    harness.submit(a, prompt_text("An ape"));
+    harness.settle();
+    assert!(matches!(
+        harness.last_message(a),
+        Some(ServerMsgRoot::PromptRejected(prompt)) if prompt.text == "An ape"
+    ));
+    assert_eq!(harness.count::<Prompt>(), 1);
+
+    // The refused prompt doesn't use up the quota
+    harness.submit(a, prompt_text("A monkey"));
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+}
+
+#[test]
+fn stroke_drawings_are_checked() {
+    let mut harness = Harness::new(vec![draw(60), prompt(1)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    let strokes = |count| {
+        let dot = VectorEdit::Stroke(VectorStroke {
+            points: vec![[256, 256]],
+            size: 0,
+            color: 0,
+            eraser: false,
+        });
+        ClientMsgComm::SubmitDrawing(Drawing {
+            drawing: Picture::Strokes(strokes::encode(&vec![dot; count])),
+            bg_color: [255; 3],
+        })
+    };
+
+    harness.submit(a, strokes(MAX_STROKES + 1));
+    harness.submit(b, strokes(1));
+    harness.settle();
+    assert_eq!(harness.count::<Drawing>(), 1);
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+
+    harness.submit(a, strokes(MAX_STROKES));
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+}
+
+#[test]
+fn pixel_drawings_must_fill_the_canvas() {
+    let mut harness = Harness::new(vec![draw(60), prompt(1)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+
+    let small = ClientMsgComm::SubmitDrawing(Drawing {
+        drawing: Picture::Pixels(vec![0; 16]),
+        bg_color: [255; 3],
+    });
+    harness.submit(a, small);
+    harness.submit(b, drawing());
+    harness.settle();
+    assert_eq!(harness.count::<Drawing>(), 1);
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+
+    harness.submit(a, drawing());
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+}
+
+#[test]
+fn disconnect_mid_phase() {
+    let mut harness = Harness::new(vec![draw(60), prompt(1)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    let c = harness.connect("c");
+    harness.settle();
+
+    harness.submit(a, drawing());
+    harness.submit(b, drawing());
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+
+    harness.send(c, ClientMsgRoot::Disconnect);
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+    assert!(!harness.is_connected(c));
+    assert!(harness.messages(c).is_empty());
+    assert!(matches!(
+        harness.last_message(a),
+        Some(ServerMsgRoot::Prompt { .. })
+    ));
+}
+
+#[test]
+fn end_of_schedule_cleans_up_room() {
+    let mut harness = Harness::new(vec![draw(60)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    // Wait, host, draw and its progress
+    assert_eq!(harness.messages(a).len(), 4);
+
+    harness.submit(a, drawing());
+    harness.submit(b, drawing());
+    harness.step();
+    assert_eq!(harness.count::<Drawing>(), 2);
+
+    // Room goes back to waiting, then starts a new match
+    harness.settle();
+    assert_eq!(harness.count::<Drawing>(), 0);
+    assert_eq!(harness.game_state(), Some(GameState::Draw));
+    // Progress of the last phase, then the new one
+    assert!(matches!(
+        harness.messages(a)[..],
+        [
+            ServerMsgRoot::Progress(_),
+            ServerMsgRoot::Draw { .. },
+            ServerMsgRoot::Progress(_)
+        ]
+    ));
+}
+
+#[test]
+fn everyone_leaving_stops_the_room() {
+    let mut harness = Harness::new(vec![draw(60)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    harness.settle();
+    assert_eq!(harness.room_state(), Some(RoomState::Running));
+
+    harness.send(a, ClientMsgRoot::Disconnect);
+    harness.send(b, ClientMsgRoot::Disconnect);
+    harness.settle();
+    assert_eq!(harness.room_state(), Some(RoomState::Waiting));
+}
+
+#[test]
+fn lost_connection_counts_as_leaving() {
+    let mut harness = Harness::new(vec![draw(60), prompt(1)]);
+    let a = harness.connect("a");
+    let b = harness.connect("b");
+    let c = harness.connect("c");
+    harness.settle();
+
+    harness.submit(a, drawing());
+    harness.submit(b, drawing());
+    harness.drop_connection(c);
+    harness.settle();
+    assert_eq!(harness.game_state(), Some(GameState::Prompt));
+}
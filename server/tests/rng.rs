@@ -0,0 +1,78 @@
+mod harness;
+
+use common::{
+    game::Vote,
+    protocol::{ClientMsgComm, ServerMsgRoot},
+};
+use harness::Harness;
+use server::{rng::MatchSeed, states::RoomState};
+
+/// Play a match with a fixed seed, returning what every player received.
+fn play(seed: u64, answer: impl Fn(usize, &ServerMsgRoot) -> Vec<ClientMsgComm>) -> Vec<Vec<u8>> {
+    let mut harness = Harness::new(harness::schedule());
+    harness.app.insert_resource(MatchSeed(Some(seed)));
+    let players = ["a", "b", "c"].map(|name| harness.connect(name));
+    harness.settle();
+    assert_eq!(harness.room_state(), Some(RoomState::Running));
+    harness
+        .play_match(&players, answer)
+        .iter()
+        .map(|received| bincode::serialize(received).unwrap())
+        .collect()
+}
+
+/// Players split their votes, the last one doesn't vote.
+fn split_votes(player: usize, message: &ServerMsgRoot) -> Vec<ClientMsgComm> {
+    match message {
+        ServerMsgRoot::Vote {
+            combination1,
+            combination2,
+            ..
+        } => match player {
+            0 => vec![ClientMsgComm::SubmitVote(Vote {
+                combination: combination1.0,
+            })],
+            1 => vec![ClientMsgComm::SubmitVote(Vote {
+                combination: combination2.0,
+            })],
+            _ => vec![],
+        },
+        _ => harness::answer(player, message),
+    }
+}
+
+fn tie_winner(seed: u64) -> String {
+    let mut harness = Harness::new(harness::schedule());
+    harness.app.insert_resource(MatchSeed(Some(seed)));
+    let players = ["a", "b", "c"].map(|name| harness.connect(name));
+    harness.settle();
+    let received = harness.play_match(&players, split_votes);
+    let winner = received[0].iter().find_map(|message| match message {
+        ServerMsgRoot::Winner { winner, .. } => Some(winner.prompt.text.clone()),
+        _ => None,
+    });
+    let vote = received[0].iter().find_map(|message| match message {
+        ServerMsgRoot::Vote { combination1, .. } => Some(combination1.2.text.clone()),
+        _ => None,
+    });
+    // Name which side won
+    if winner == vote {
+        "first".to_owned()
+    } else {
+        "second".to_owned()
+    }
+}
+
+#[test]
+fn same_seed_plays_the_same_match() {
+    assert_eq!(play(3, harness::answer), play(3, harness::answer));
+    assert_ne!(play(3, harness::answer), play(4, harness::answer));
+}
+
+#[test]
+fn ties_are_broken_by_the_seed() {
+    let winners = (0..8).map(tie_winner).collect::<Vec<_>>();
+    assert_eq!(winners, (0..8).map(tie_winner).collect::<Vec<_>>());
+    assert!(winners.iter().any(|w| w == "first"));
+    assert!(winners.iter().any(|w| w == "second"));
+}
@@ -0,0 +1,120 @@
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use common::protocol::{ClientMsgRoot, ServerMsgRoot};
+use server::{
+    transport::websocket::{WebSocketServer, WebSocketTransportPlugin},
+    ServerPlugin,
+};
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+fn connect(app: &App, name: &str) -> Socket {
+    let address = app.world().resource::<WebSocketServer>().local_addr();
+    let (mut socket, _) = tungstenite::connect(format!("ws://{address}")).unwrap();
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+    }
+    let connect = ClientMsgRoot::Connect {
+        name: name.to_owned(),
+    };
+    let text = serde_json::to_string(&connect).unwrap();
+    socket.send(Message::Text(text)).unwrap();
+    socket
+}
+
+/// Update the server until the socket receives a message.
+fn receive(app: &mut App, socket: &mut Socket) -> ServerMsgRoot {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        app.update();
+        if let Ok(Message::Text(text)) = socket.read() {
+            return serde_json::from_str(&text).unwrap();
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    panic!("No message received");
+}
+
+#[test]
+fn clients_join_over_websocket() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        ServerPlugin,
+        WebSocketTransportPlugin {
+            address: "127.0.0.1:0".to_owned(),
+            ..default()
+        },
+    ));
+    app.update();
+    app.update();
+
+    let mut a = connect(&app, "a");
+    assert!(matches!(receive(&mut app, &mut a), ServerMsgRoot::Wait));
+    assert!(matches!(receive(&mut app, &mut a), ServerMsgRoot::Host));
+    let mut b = connect(&app, "b");
+    assert!(matches!(receive(&mut app, &mut b), ServerMsgRoot::Wait));
+
+    // Two players start the game
+    assert!(matches!(
+        receive(&mut app, &mut a),
+        ServerMsgRoot::Draw { .. }
+    ));
+    assert!(matches!(
+        receive(&mut app, &mut b),
+        ServerMsgRoot::Draw { .. }
+    ));
+}
+
+#[test]
+fn stalled_handshakes_time_out_and_connections_are_limited() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        ServerPlugin,
+        WebSocketTransportPlugin {
+            address: "127.0.0.1:0".to_owned(),
+            max_connections: 1,
+            handshake_timeout: Duration::from_millis(300),
+        },
+    ));
+    app.update();
+    app.update();
+    let address = app.world().resource::<WebSocketServer>().local_addr();
+
+    // A client that never sends its handshake takes the only slot
+    let _stalled = TcpStream::connect(address).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert!(tungstenite::connect(format!("ws://{address}")).is_err());
+
+    // Until its handshake times out
+    thread::sleep(Duration::from_millis(400));
+    let mut a = connect(&app, "a");
+    assert!(matches!(receive(&mut app, &mut a), ServerMsgRoot::Wait));
+}
+
+#[test]
+fn busy_address_stops_the_server() {
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        ServerPlugin,
+        WebSocketTransportPlugin {
+            address: taken.local_addr().unwrap().to_string(),
+            ..default()
+        },
+    ));
+    app.update();
+    app.update();
+    assert!(app.world().get_resource::<WebSocketServer>().is_none());
+    assert!(matches!(app.should_exit(), Some(AppExit::Error(_))));
+}